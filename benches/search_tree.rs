@@ -0,0 +1,247 @@
+//! Compares readouts on the arena search tree with readouts on a petgraph StableGraph, the structure
+//! that the old SearchTree was built on.
+//! This is a synthetic comparison: the StableGraph side is a minimal readout written for this bench, not
+//! the old SearchTree itself, which did its inference, noise and bookkeeping inline and can't be run
+//! against the current Game and Inference traits. Both trees are built with the same shape and priors,
+//! and both readouts use the same PUCT formula, so the numbers only measure the cost of navigating the
+//! tree: neighbor iteration and find_edge lookups, versus contiguous edge arrays and a recorded path.
+//! "search tree read" times the real SearchTree end to end.
+#[macro_use]
+extern crate criterion;
+
+use criterion::{black_box, Criterion};
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableGraph};
+use seraphim::{
+    game, inference,
+    search::{
+        self,
+        tree::{NodeIdx, Successor, Tree},
+    },
+};
+
+const BRANCHING: usize = 9;
+const DEPTH: usize = 4;
+const CPUCT: f64 = 1.5;
+
+fn stimulus(prior: f32, visit_count: usize, total_value: f64, parent_visits: usize) -> f64 {
+    let n = visit_count as f64 + 1.0;
+    let q = total_value / n;
+    q + CPUCT * prior as f64 * (parent_visits as f64 / n).sqrt()
+}
+
+fn by_stimulus<T>(a: &(T, f64), b: &(T, f64)) -> std::cmp::Ordering {
+    a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+// The statistics that the StableGraph search tree kept
+struct GraphNode {
+    visits: usize,
+}
+struct GraphEdge {
+    prior: f32,
+    visit_count: usize,
+    total_value: f64,
+}
+type Graph = StableGraph<GraphNode, GraphEdge>;
+
+fn build_graph() -> (Graph, NodeIndex) {
+    let mut graph = Graph::new();
+    let root = graph.add_node(GraphNode { visits: 0 });
+    let mut frontier = vec![root];
+    for _ in 0..DEPTH {
+        let mut next = vec![];
+        for parent in frontier {
+            for _ in 0..BRANCHING {
+                let child = graph.add_node(GraphNode { visits: 0 });
+                graph.add_edge(
+                    parent,
+                    child,
+                    GraphEdge {
+                        prior: 1.0 / BRANCHING as f32,
+                        visit_count: 0,
+                        total_value: 0.0,
+                    },
+                );
+                next.push(child);
+            }
+        }
+        frontier = next;
+    }
+    (graph, root)
+}
+
+fn graph_parent(graph: &Graph, idx: NodeIndex) -> Option<(NodeIndex, EdgeIndex)> {
+    let parent = graph
+        .neighbors_directed(idx, petgraph::Direction::Incoming)
+        .next()?;
+    Some((parent, graph.find_edge(parent, idx).unwrap()))
+}
+
+// One readout, navigating the tree the way the StableGraph search did
+fn graph_readout(graph: &mut Graph, root: NodeIndex) {
+    let mut idx = root;
+    while graph.neighbors(idx).next().is_some() {
+        graph[idx].visits += 1;
+        let parent_visits = graph[idx].visits;
+        let (next, _) = graph
+            .neighbors(idx)
+            .map(|child| {
+                let (_, edge_idx) = graph_parent(graph, child).unwrap();
+                let edge = &graph[edge_idx];
+                (
+                    child,
+                    stimulus(
+                        edge.prior,
+                        edge.visit_count,
+                        edge.total_value,
+                        parent_visits,
+                    ),
+                )
+            })
+            .max_by(by_stimulus)
+            .unwrap();
+        idx = next;
+    }
+    let mut value = 1.0;
+    while idx != root {
+        let (parent, edge_idx) = graph_parent(graph, idx).unwrap();
+        let edge = &mut graph[edge_idx];
+        edge.visit_count += 1;
+        edge.total_value += value;
+        value = -value;
+        idx = parent;
+    }
+}
+
+fn build_arena() -> (Tree<usize>, NodeIdx) {
+    let tree = Tree::new();
+    let root = tree.add_root(0);
+    let mut frontier = vec![root];
+    for depth in 0..DEPTH {
+        let mut next = vec![];
+        for parent in frontier {
+            tree.begin_expansion(parent).unwrap().finish(
+                0.0,
+                (0..BRANCHING).map(|action| Successor {
+                    action,
+                    prior: 1.0 / BRANCHING as f32,
+                    raw_prior: 1.0 / BRANCHING as f32,
+                    state: depth + 1,
+                }),
+            );
+            next.extend(tree[parent].edges().iter().map(|edge| edge.child()));
+        }
+        frontier = next;
+    }
+    (tree, root)
+}
+
+// One readout, navigating the tree the way the arena search does
+fn arena_readout(tree: &Tree<usize>, root: NodeIdx) {
+    let mut path = Vec::with_capacity(DEPTH);
+    let mut node = &tree[root];
+    while !node.edges().is_empty() {
+        node.add_visit();
+        let parent_visits = node.visits();
+        let (edge, _) = node
+            .edges()
+            .iter()
+            .map(|edge| {
+                (
+                    edge,
+                    stimulus(
+                        edge.prior(),
+                        edge.visit_count(),
+                        edge.total_value(),
+                        parent_visits,
+                    ),
+                )
+            })
+            .max_by(by_stimulus)
+            .unwrap();
+        edge.add_virtual_loss();
+        path.push(edge);
+        node = &tree[edge.child()];
+    }
+    let mut value = 1.0;
+    for edge in path.iter().rev() {
+        edge.backup(value, 0, 0);
+        value = -value;
+    }
+}
+
+fn readouts(c: &mut Criterion) {
+    let (mut graph, graph_root) = build_graph();
+    c.bench_function("synthetic stable graph readout", move |b| {
+        b.iter(|| graph_readout(black_box(&mut graph), graph_root))
+    });
+
+    let (tree, tree_root) = build_arena();
+    c.bench_function("synthetic arena readout", move |b| {
+        b.iter(|| arena_readout(black_box(&tree), tree_root))
+    });
+}
+
+// Nim: take 1, 2 or 3 stones from the pile. Whoever takes the last stone wins.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Pile(u8);
+impl std::default::Default for Pile {
+    fn default() -> Self {
+        Pile(21)
+    }
+}
+impl game::GameState for Pile {
+    fn feature_bytes(&self) -> Vec<u8> {
+        vec![self.0]
+    }
+}
+
+#[derive(Debug)]
+struct Nim;
+impl game::Game for Nim {
+    type State = Pile;
+    fn action_count(&self) -> usize {
+        3
+    }
+    fn legal_actions(&self, state: &Pile) -> Vec<bool> {
+        (0..3).map(|a| state.0 as usize > a).collect()
+    }
+    fn successor(&self, state: &Pile, action: usize) -> Pile {
+        Pile(state.0 - action as u8 - 1)
+    }
+    fn status(&self, state: &Pile) -> game::GameStatus {
+        match state.0 {
+            0 => game::GameStatus::LastPlayerWon,
+            _ => game::GameStatus::InProgress,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct UniformInference;
+impl inference::Inference for UniformInference {
+    fn infer(&mut self, _input: &[u8]) -> seraphim::error::Result<inference::Priors> {
+        Ok(inference::Priors {
+            ps: vec![1.0 / 3.0; 3],
+            q: 0.0,
+        })
+    }
+}
+
+fn search(c: &mut Criterion) {
+    c.bench_function("search tree read (400 readouts)", |b| {
+        b.iter_with_setup(
+            || {
+                let options = search::SearchTreeOptions {
+                    readouts: 400,
+                    ..Default::default()
+                };
+                search::SearchTree::init_with_options(UniformInference, Nim, options)
+            },
+            |mut search_tree| search_tree.read().unwrap(),
+        )
+    });
+}
+
+criterion_group!(benches, readouts, search);
+criterion_main!(benches);
@@ -14,8 +14,9 @@ fn main() {
     // regenerate_rust_gencode();
 }
 
-// This doesn't need to be done on every build; only when the example.proto or feature.proto
-// changes, which should be quite rare, or when rust-protobuf is upgraded.
+// This doesn't need to be done on every build; only when the example.proto, feature.proto,
+// meta_graph.proto or saved_model.proto changes, which should be quite rare, or when rust-protobuf
+// is upgraded.
 // Moreover, running this requires `protoc` in $PATH, which otherwise isn't a dependency
 // of this package. So, regeneration is disabled for must builds.
 fn regenerate_rust_gencode() {
@@ -31,4 +32,16 @@ fn regenerate_rust_gencode() {
         includes: &["src"],
     })
     .expect("protoc");
+    protoc_rust::run(protoc_rust::Args {
+        out_dir: "src/inference/gen",
+        input: &[
+            "src/third_party/tensorflow/core/protobuf/meta_graph.proto",
+            "src/third_party/tensorflow/core/protobuf/saved_model.proto",
+        ],
+        customize: protoc_rust::Customize {
+            ..Default::default()
+        },
+        includes: &["src"],
+    })
+    .expect("protoc");
 }
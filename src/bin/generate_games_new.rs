@@ -1,15 +1,58 @@
-// use seraphim::generate;
-
-// fn init_logger() {
-//     flexi_logger::Logger::with_env()
-//         // .format(|record: &flexi_logger::Record| format!("{}", &record.args()))
-//         .duplicate_to_stderr(flexi_logger::Duplicate::Debug)
-//         .start()
-//         .unwrap();
-// }
-
-// fn main() {
-//     init_logger();
-//     let generator = generate::Generator::new(generate::Config::from_args());
-//     generator::run()
-// }
+use seraphim::generate;
+use seraphim::inference::batch::{BatchedInference, InferenceBatcher};
+use seraphim::inference::signature::ModelSignature;
+use seraphim::inference::TensorFlowInferenceEngine;
+use seraphim::search;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time;
+use structopt::StructOpt;
+
+fn init_logger() {
+    flexi_logger::Logger::with_env()
+        // .format(|record: &flexi_logger::Record| format!("{}", &record.args()))
+        .duplicate_to_stderr(flexi_logger::Duplicate::Debug)
+        .start()
+        .unwrap();
+}
+
+// Every game shares the one batcher, so that the champion is only loaded once per promotion. The
+// batcher loads the engine on its own thread, since a TensorFlow session can't be moved to it.
+fn load_champion(saved_model: &Path, threads: usize) -> seraphim::error::Result<BatchedInference> {
+    let saved_model = saved_model.to_path_buf();
+    InferenceBatcher::spawn(move || {
+        TensorFlowInferenceEngine::from_saved_model(
+            saved_model,
+            &ModelSignature::default(),
+            9,
+            threads,
+            time::Duration::from_millis(1),
+        )
+    })
+}
+
+fn main() {
+    init_logger();
+    let config = generate::Config::from_args();
+    // One inference per search thread per batch
+    let threads =
+        search::SearchTreeOptions::from_overrides(config.search_tree_options.clone()).threads;
+    let generator = generate::GameGenerator::new(config);
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    match generator.run(move |dir: &Path| load_champion(dir, threads), &running) {
+        Ok(resignations) => println!("{}", resignations),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use failure::Backtrace;
 use std::io;
+#[cfg(feature = "tensorflow")]
 use tensorflow;
 
 pub type Result<T> = std::result::Result<T, failure::Error>;
@@ -13,14 +14,51 @@ pub enum ModelError {
         #[fail(cause)]
         io_error: io::Error,
     },
+    #[cfg(feature = "tensorflow")]
     #[fail(display = "Couldn't load model from {:?}", dir)]
     CouldntLoad {
         dir: std::path::PathBuf,
         #[fail(cause)]
         tf: TensorflowError,
     },
+    #[fail(display = "Couldn't read the SavedModel metadata in {:?}", path)]
+    CouldntReadMetadata {
+        path: std::path::PathBuf,
+        #[fail(cause)]
+        io_error: io::Error,
+    },
+    #[fail(display = "{:?} isn't a valid SavedModel.", path)]
+    MalformedMetadata {
+        path: std::path::PathBuf,
+        #[fail(cause)]
+        protobuf_error: protobuf::ProtobufError,
+    },
+    #[fail(display = "Couldn't load the ONNX model {:?}:\n{}", path, msg)]
+    CouldntLoadOnnx {
+        path: std::path::PathBuf,
+        msg: String,
+    },
+    #[fail(
+        display = "The SavedModel in {:?} has no SignatureDef '{}' in a MetaGraphDef tagged {:?}.",
+        dir, signature_def, tags
+    )]
+    SignatureDefNotFound {
+        signature_def: String,
+        tags: Vec<String>,
+        dir: std::path::PathBuf,
+    },
+    #[fail(
+        display = "SignatureDef '{}' of the SavedModel in {:?} has no tensor '{}'.",
+        signature_def, dir, key
+    )]
+    SignatureKeyNotFound {
+        key: String,
+        signature_def: String,
+        dir: std::path::PathBuf,
+    },
 }
 
+#[cfg(feature = "tensorflow")]
 #[derive(Debug, Fail)]
 #[fail(display = "Tensorflow returned an error:\n{}", msg)]
 pub struct TensorflowError {
@@ -29,6 +67,7 @@ pub struct TensorflowError {
     msg: String,
 }
 
+#[cfg(feature = "tensorflow")]
 impl From<tensorflow::Status> for TensorflowError {
     fn from(status: tensorflow::Status) -> Self {
         TensorflowError {
@@ -39,10 +78,71 @@ impl From<tensorflow::Status> for TensorflowError {
     }
 }
 
+#[derive(Debug, Fail)]
+pub enum InferenceError {
+    #[fail(display = "The inference batcher has shut down.")]
+    BatcherDisconnected,
+    #[fail(display = "Batched inference failed:\n{}", msg)]
+    BatchFailed { msg: String },
+    #[fail(
+        display = "Every input in a batch must have the same length, but got {} and {}.",
+        expected, actual
+    )]
+    RaggedBatch { expected: usize, actual: usize },
+    #[cfg(feature = "tensorflow")]
+    #[fail(
+        display = "Running op '{}' on an input of shape {:?} failed for the model in {:?}:\n{}",
+        op, shape, dir, tf
+    )]
+    SessionFailed {
+        op: String,
+        shape: Vec<u64>,
+        dir: std::path::PathBuf,
+        #[fail(cause)]
+        tf: TensorflowError,
+    },
+    #[fail(
+        display = "Output '{}' of the model in {:?} has {} floats, but {} were expected for this batch.",
+        op, dir, actual, expected
+    )]
+    WrongOutputLength {
+        op: String,
+        dir: std::path::PathBuf,
+        expected: usize,
+        actual: usize,
+    },
+    #[fail(
+        display = "Running the ONNX model {:?} on an input of shape {:?} failed:\n{}",
+        path, shape, msg
+    )]
+    OnnxFailed {
+        path: std::path::PathBuf,
+        shape: Vec<usize>,
+        msg: String,
+    },
+    #[fail(
+        display = "The engine for the champion in {:?} can't be shared between games. Wrap it in a BatchedInference.",
+        dir
+    )]
+    NotShareable { dir: std::path::PathBuf },
+    #[fail(
+        display = "Inference returned {} priors, but {} were expected.",
+        actual, expected
+    )]
+    WrongPriorCount { expected: usize, actual: usize },
+}
+
 #[derive(Debug, Fail)]
 pub enum SearchError {
     #[fail(display = "Invalid configuration: {}", msg)]
     InvalidConfiguration { msg: String },
+    #[fail(
+        display = "Search tree snapshot has format version {}, but this version of seraphim reads version {}.",
+        version, expected
+    )]
+    IncompatibleSnapshot { version: u32, expected: u32 },
+    #[fail(display = "Search tree snapshot is malformed: {}", msg)]
+    MalformedSnapshot { msg: String },
     #[fail(display = "Unspecified error when searching:\n{}", msg)]
     Unspecified { msg: String, backtrace: Backtrace },
 }
@@ -8,6 +8,7 @@ pub enum GameStatus {
     Draw,
     LastPlayerWon,
     LastPlayerLost,
+    PlayerToMoveResigned, // The search resigned the game instead of moving, so the last player won
 }
 
 impl std::default::Default for GameStatus {
@@ -17,7 +18,13 @@ impl std::default::Default for GameStatus {
 }
 
 pub trait GameState:
-    std::cmp::Eq + std::hash::Hash + std::clone::Clone + std::fmt::Debug + std::default::Default
+    std::cmp::Eq
+    + std::hash::Hash
+    + std::clone::Clone
+    + std::fmt::Debug
+    + std::default::Default
+    + std::marker::Send
+    + std::marker::Sync
 {
     // Each training example will be recorded as this bytestring (representing the game state)
     // concatenated to packed f32s representing the trainable probabilities
@@ -27,7 +34,8 @@ pub trait GameState:
     fn feature_bytes(&self) -> Vec<u8>;
 }
 
-pub trait Game {
+// Games are shared by the threads of a parallel search
+pub trait Game: std::marker::Sync {
     type State: GameState;
 
     // How many different actions is it possible to sample in the game?
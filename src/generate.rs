@@ -1,32 +1,26 @@
-#![cfg_attr(feature = "nightly", feature(alloc_system))]
-#[cfg(feature = "nightly")]
-extern crate alloc_system;
-extern crate clap;
-extern crate flexi_logger;
-extern crate fs2;
-extern crate retry;
-extern crate seraphim;
-#[macro_use]
-extern crate log;
-#[macro_use]
-extern crate structopt;
-
-use seraphim::search;
-extern crate ctrlc;
+//! Generates games of Tic Tac Toe by self play, for train.py to learn from. Games are played with the
+//! current champion, which is reloaded between games whenever train.py promotes a new one, and each
+//! move is written to a .tfrecord file in the output directory as a tf.train.Example. Inferences are
+//! cached across games, since self-play keeps reaching the same positions, until the champion changes.
+use crate::error::Result;
+use crate::game::{Game, GameState, GameStatus};
+use crate::inference::cache::{CachingInference, InferenceCache};
+use crate::inference::reload::{self, ReloadingInference};
+use crate::inference::Inference;
+use crate::io::tf::RecordWriter;
+use crate::search::{self, resign::ResignationStats, SearchTree};
+use crate::tictactoe::{self, gen};
 
+use fs2::FileExt;
+use protobuf::Message;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-
-use fs2::FileExt;
-use std::io;
-use std::io::{Read, Seek, Write};
-use std::result::Result;
 use std::time;
 
-use structopt::StructOpt;
-
-static CONTROL_FILE: &'static str = "control";
-static MODEL_DIR_PREFIX: &'static str = "models";
+static CONTROL_FILE: &str = "control";
 
 /// Utility for generating new games of self play for reinforcement learning.
 #[derive(Debug, StructOpt, Clone)]
@@ -36,191 +30,258 @@ static MODEL_DIR_PREFIX: &'static str = "models";
 )]
 pub struct Config {
     #[structopt(long, default_value = "100", help = "How many games in each tfrecord?")]
-    games_per_file: i64,
+    pub games_per_file: i64,
 
     #[structopt(
         long,
         default_value = "50",
         help = "How many .tfrecord files to keep in output_dir."
     )]
-    max_files: i64,
+    pub max_files: i64,
 
     #[structopt(short = "d", long)]
-    debug: bool,
+    pub debug: bool,
 
     #[structopt(
         long,
         help = "Write game data to this path instead of the default $SERAPHIM_DATA/gamedata/$SERAPHIM_MODEL_NAME"
     )]
-    output_dir: Option<String>,
+    pub output_dir: Option<String>,
+
+    #[structopt(
+        long,
+        default_value = "100000",
+        help = "How many positions' inferences to keep between games. 0 disables the cache."
+    )]
+    pub cache_size: usize,
 
     #[structopt(flatten)]
-    seraphim_config: seraphim::search::SeraphimConfig,
+    pub seraphim_config: search::SeraphimConfig,
 
     #[structopt(flatten)]
-    search_tree_options: seraphim::search::SearchTreeParamOverrides,
+    pub search_tree_options: search::SearchTreeParamOverrides,
 }
 
-struct GameGenerator {
-    config: Config
+pub struct GameGenerator {
+    config: Config,
+    cache: InferenceCache, // Shared by every game and every champion's engine
 }
 impl GameGenerator {
     pub fn new(config: Config) -> Self {
-        GameGenerator { config }
+        let cache = InferenceCache::new(config.cache_size);
+        GameGenerator { config, cache }
+    }
+
+    pub fn cache(&self) -> &InferenceCache {
+        &self.cache
     }
-    pub fn run () {
-        let seraphim_config = opts.seraphim_config;
-        let mut overrides = opts.search_tree_options;
+
+    // Play games until running is cleared, with the champion that load loads from its saved_model
+    // directory, e.g. a TensorFlowInferenceEngine wrapped in an InferenceBatcher. Returns how often the
+    // games resigned, and how often the ones played out with resignation disabled would have done so
+    // wrongly.
+    pub fn run<I, L>(&self, load: L, running: &AtomicBool) -> Result<ResignationStats>
+    where
+        I: Inference + Send,
+        L: Fn(&Path) -> Result<I> + Send + Sync + 'static,
+    {
+        let seraphim_config = &self.config.seraphim_config;
+        let mut overrides = self.config.search_tree_options.clone();
         overrides.dirichlet_alpha.get_or_insert(0.5);
         overrides.cpuct.get_or_insert(1.0);
         overrides.tempering_point.get_or_insert(1);
-        let search_tree_options = seraphim::search::SearchTreeOptions::from_overrides(overrides);
-
-        let fq_model_dir = format!(
-            "{}/{}/{}/{}/{}",
-            seraphim_config.seraphim_data, MODEL_DIR_PREFIX, &seraphim_config.model_name, "champion", "saved_model"
-        );
-        let lock_path = format!(
-            "{}/{}/{}/{}/{}",
-            seraphim_config.seraphim_data, MODEL_DIR_PREFIX, &seraphim_config.model_name, "champion", "lock"
-        );
-
-        let output_dir = opts
-            .output_dir
-            .unwrap_or_else(|| format!("{}/gamedata/{}", seraphim_config.seraphim_data, seraphim_config.model_name));
-
-        let running = Arc::new(AtomicBool::new(true));
-        let r = running.clone();
+        let search_tree_options = search::SearchTreeOptions::from_overrides(overrides);
+
+        let champion_dir =
+            reload::champion_dir(&seraphim_config.seraphim_data, &seraphim_config.model_name);
+
+        // It might take a few seconds before the initialized model appears when starting a new training session
+        // - rather than immediately bailling, retry a few times. After that, the champion is reloaded between
+        // games whenever train.py promotes a new one.
+        let load = Arc::new(load);
+        let mut attempts = 0;
+        let mut champion = loop {
+            let load = load.clone();
+            let cache = self.cache.clone();
+            let load_cached =
+                move |dir: &Path| Ok(CachingInference::with_cache(load(dir)?, cache.clone()));
+            match ReloadingInference::new(&champion_dir, load_cached) {
+                Ok(champion) => break champion.clearing_on_reload(self.cache.clone()),
+                Err(err) => {
+                    attempts += 1;
+                    if attempts == 5 {
+                        error!("Couldn't restore a model from {:?}. \nTry running 'src/tictactoe/train.py --init'", champion_dir);
+                        return Err(err);
+                    }
+                    warn!("Couldn't restore a model from {:?}:\n{}", champion_dir, err);
+                    std::thread::sleep(time::Duration::from_secs(10));
+                }
+            }
+        };
 
-        ctrlc::set_handler(move || {
-            r.store(false, Ordering::SeqCst);
-        })
-        .expect("Error setting Ctrl-C handler");
+        let output_dir = self.config.output_dir.clone().unwrap_or_else(|| {
+            format!(
+                "{}/gamedata/{}",
+                seraphim_config.seraphim_data, seraphim_config.model_name
+            )
+        });
 
         let mut count = 0;
         let mut draws = 0;
-        'outer: while running.load(Ordering::SeqCst) {
-            ::std::fs::create_dir_all(&output_dir).unwrap();
+        let mut resignations = ResignationStats::default();
+        while running.load(Ordering::SeqCst) {
+            ::std::fs::create_dir_all(&output_dir)?;
 
-            let next_id = get_next_file_id(&output_dir).unwrap();
-            println!("{}", next_id);
+            let next_id = get_next_file_id(&output_dir)?;
+            info!("Writing batch {}", next_id);
             let next_file_path = format!("{}/batch-{:07}", &output_dir, next_id);
 
-            let lock = ::std::fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(&lock_path)
-                .unwrap();
-
-            let _ = lock.lock_exclusive();
-
             let file = ::std::fs::OpenOptions::new()
                 .write(true)
                 .truncate(true)
                 .create(true)
-                .open(next_file_path.clone())
-                .unwrap();
+                .open(next_file_path.clone())?;
 
             let writer = ::std::io::BufWriter::new(file);
 
-            // It might take a few seconds before the initialized model appears when starting a new training session
-            // - rather than immediately bailling, retry a few times.
-            match retry::retry(
-                5,
-                1000 * 10,
-                || seraphim::tictactoe::DnnGameExpert::from_saved_model(&fq_model_dir),
-                |ge| ge.is_ok(),
-            ) {
-                Ok(ge) => {
-                    let _ = lock.unlock();
-                    match do_some_games(
-                        &mut ge.unwrap(),
-                        opts.games_per_file,
-                        writer,
-                        &search_tree_options,
-                        running.clone(),
-                    ) {
-                        Ok((c, d)) => {
-                            count += c;
-                            draws += d;
-                        }
-                        Err(err) => {
-                            println!("{:?}", err);
-                            break;
-                        }
-                    }
-                }
-                Err(err) => {
-                    panic!("Couldn't restore a model from '{}'. \nTry running 'src/tictactoe/train.py --init'\nError:\n{:?}", fq_model_dir,  err);
-                }
-            };
+            let (c, d) = do_some_games(
+                &mut champion,
+                self.config.games_per_file,
+                writer,
+                &search_tree_options,
+                running,
+                &mut resignations,
+            )?;
+            count += c;
+            draws += d;
+            // The false positive rate is what resign_threshold should be tuned by
+            info!("{}", resignations);
+            info!(
+                "Inference cache: {} hits, {} misses",
+                self.cache.hits(),
+                self.cache.misses()
+            );
 
             // changing files in gamedata is potentially racing with training processes that are reading
             // .tfrecord files.
             let mut stale_index = ::std::fs::OpenOptions::new()
-                .write(true)
                 .create(true)
                 .append(true)
-                .open(&format!("{}/stale_file_paths", output_dir))
-                .unwrap();
+                .open(format!("{}/stale_file_paths", output_dir))?;
 
-            stale_index.lock_exclusive().unwrap();
+            stale_index.lock_exclusive()?;
 
             std::fs::rename(
                 next_file_path.clone(),
                 format!("{}.tfrecord", next_file_path),
-            )
-            .unwrap();
-
-            if next_id - opts.max_files >= 0 {
-                stale_index
-                    .write_fmt(format_args!(
-                        "{}/batch-{:07}.tfrecord\n",
-                        output_dir,
-                        next_id - opts.max_files
-                    ))
-                    .unwrap();
+            )?;
+
+            if next_id - self.config.max_files >= 0 {
+                stale_index.write_fmt(format_args!(
+                    "{}/batch-{:07}.tfrecord\n",
+                    output_dir,
+                    next_id - self.config.max_files
+                ))?;
             }
-            stale_index.unlock().unwrap();
-            lock.unlock().unwrap();
+            stale_index.unlock()?;
+        }
+
+        info!("Drew {} / {} games", draws, count);
+        Ok(resignations)
+    }
+}
+
+// Plays a game to the end and writes one tf.train.Example for each move that is a policy_target: the
+// position it was played from ("game", its feature_bytes), the search's posteriors ("choice"), and the
+// version of the champion that played the game ("model_version"), so that training can tell which
+// examples came from which model.
+pub fn play_and_record_one_game<I, S, G, W>(
+    searcher: &mut SearchTree<I, S, G>,
+    model_version: &str,
+    writer: &mut RecordWriter<W>,
+) -> Result<GameStatus>
+where
+    I: Inference + Send,
+    S: GameState,
+    G: Game<State = S>,
+    W: Write,
+{
+    let model_version = bytes_feature(model_version.as_bytes().to_vec());
+    while let GameStatus::InProgress = searcher.status() {
+        let results = searcher.read()?;
+
+        // Moves that got a cheap search under playout cap randomization are played, but not recorded
+        if results.policy_target {
+            let mut features_map = HashMap::new();
+            features_map.insert(
+                "game".to_string(),
+                bytes_feature(searcher.current_state_ref().feature_bytes()),
+            );
+            features_map.insert(
+                "choice".to_string(),
+                floats_feature(results.results.clone()),
+            );
+            features_map.insert("model_version".to_string(), model_version.clone());
+
+            let mut features = gen::feature::Features::new();
+            features.set_feature(features_map);
+            let mut example = gen::example::Example::new();
+            example.set_features(features);
+            writer.write_one_record(&example.write_to_bytes()?)?;
         }
 
-        println!("Drew {} / {} games", draws, count);
+        searcher.apply_search_results(&results);
     }
-    
+    Ok(searcher.status())
 }
 
+fn bytes_feature(bytes: Vec<u8>) -> gen::feature::Feature {
+    let mut bytes_list = gen::feature::BytesList::new();
+    bytes_list.set_value(::protobuf::RepeatedField::from_vec(vec![bytes]));
+    let mut feature = gen::feature::Feature::new();
+    feature.set_bytes_list(bytes_list);
+    feature
+}
+
+fn floats_feature(floats: Vec<f32>) -> gen::feature::Feature {
+    let mut float_list = gen::feature::FloatList::new();
+    float_list.set_value(floats);
+    let mut feature = gen::feature::Feature::new();
+    feature.set_float_list(float_list);
+    feature
+}
 
 fn get_next_file_id(output_dir: &str) -> io::Result<i64> {
     let mut control = ::std::fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(false)
         .read(true)
-        .open(&format!("{}/{}", output_dir, CONTROL_FILE))?;
+        .open(format!("{}/{}", output_dir, CONTROL_FILE))?;
 
     let mut buf = Vec::new();
     control.read_to_end(&mut buf)?;
-    let val;
-
-    if buf.len() == 0 {
-        val = 0;
+    let val = if buf.is_empty() {
+        0
     } else {
         let valstr = std::str::from_utf8(&buf).unwrap();
-        val = valstr.parse::<i64>().unwrap();
-    }
+        valstr.parse::<i64>().unwrap()
+    };
     control.set_len(0)?;
     control.seek(std::io::SeekFrom::Start(0))?;
     write!(control, "{}", val + 1)?;
     Ok(val)
 }
 
-fn do_some_games<W: Write>(
-    ge: &mut seraphim::tictactoe::DnnGameExpert,
+fn do_some_games<I: Inference + Send, W: Write>(
+    champion: &mut ReloadingInference<I>,
     num: i64,
-    mut writer: W,
-    options: &seraphim::search::SearchTreeOptions,
-    running: Arc<AtomicBool>,
-) -> Result<(i64, usize), io::Error> {
+    writer: W,
+    options: &search::SearchTreeOptions,
+    running: &AtomicBool,
+    resignations: &mut ResignationStats,
+) -> Result<(i64, usize)> {
+    let mut writer = RecordWriter::new(writer);
     let mut count = 0;
     let mut draws = 0;
     let now = time::Instant::now();
@@ -229,37 +290,236 @@ fn do_some_games<W: Write>(
         if !running.load(Ordering::SeqCst) {
             break;
         }
-        let initial_search_state = seraphim::tictactoe::State::new();
-        let searcher = search::SearchTree::init_with_options(initial_search_state, options.clone());
-
-        let res = ge.play_and_record_one_game(searcher, &mut writer);
-        if let Err(err) = res {
-            error!("Error while playing a game: {:?}", err);
-            return Ok((count, draws));
-        } else if let Ok(status) = res {
-            match status {
-                seraphim::search::GameStatus::Draw => {
-                    draws += 1;
-                }
-                _ => {}
-            }
+        // Each game is played with whichever champion was current when it started
+        let inference = champion.next_game()?;
+        let model_version = inference.version().to_string();
+        let mut searcher =
+            SearchTree::init_with_options(inference, tictactoe::TicTacToe::new(), options.clone());
+
+        if let GameStatus::Draw =
+            play_and_record_one_game(&mut searcher, &model_version, &mut writer)?
+        {
+            draws += 1;
         }
+        resignations.record(&searcher);
 
         count += 1;
         if count % 1000 == 0 {
-            let _ = writer.flush();
+            writer.flush()?;
         }
     }
-    let _ = writer.flush();
-    let elapsed = now.elapsed();
-    let sec = (elapsed.as_secs() as f64) + (elapsed.subsec_nanos() as f64 / 1000_000_000.0);
-    println!(
+    writer.flush()?;
+    let sec = now.elapsed().as_secs_f64();
+    info!(
         "{} games in {:.2} sec ({:.2} games/sec)",
         count,
         sec,
         count as f64 / sec
     );
-    println!("Drew {} / {}", draws, count);
+    info!("Drew {} / {}", draws, count);
 
     Ok((count, draws))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::Priors;
+    use crate::io::tf::RecordReader;
+    use std::path::PathBuf;
+    use structopt::StructOpt;
+
+    // Uniform priors. Clears running when it's first asked, so that the generator stops after one game.
+    #[derive(Clone, Debug)]
+    struct Uniform {
+        running: Arc<AtomicBool>,
+    }
+    impl Inference for Uniform {
+        fn infer(&mut self, _input: &[u8]) -> Result<Priors> {
+            self.running.store(false, Ordering::SeqCst);
+            Ok(Priors {
+                ps: vec![1.0 / 9.0; 9],
+                q: 0.0,
+            })
+        }
+        fn try_clone(&self) -> Option<Self> {
+            Some(self.clone())
+        }
+    }
+
+    // $SERAPHIM_DATA for a model "m" whose champion has version 3
+    fn seraphim_data(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("seraphim-generate-{}-{}", std::process::id(), name));
+        let champion_dir = reload::champion_dir(dir.to_str().unwrap(), "m");
+        std::fs::create_dir_all(&champion_dir).unwrap();
+        std::fs::write(champion_dir.join("version"), "3\n").unwrap();
+        dir
+    }
+
+    fn config(seraphim_data: &Path) -> Config {
+        Config::from_iter(&[
+            "generate_games",
+            "--model_name",
+            "m",
+            "--seraphim_data",
+            seraphim_data.to_str().unwrap(),
+            "--readouts",
+            "16",
+        ])
+    }
+
+    // The features of every Example in a .tfrecord
+    fn read_examples(bytes: &[u8]) -> Vec<HashMap<String, gen::feature::Feature>> {
+        RecordReader::new(bytes)
+            .into_iter()
+            .map(|record| {
+                let mut example: gen::example::Example =
+                    protobuf::parse_from_bytes(&record.unwrap()).unwrap();
+                example.mut_features().take_feature()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_move_is_recorded_with_the_champion_version() {
+        let data = seraphim_data("record");
+        let running = Arc::new(AtomicBool::new(true));
+        let uniform = Uniform {
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let config = config(&data);
+        let mut champion = ReloadingInference::new(
+            reload::champion_dir(&config.seraphim_config.seraphim_data, "m"),
+            move |_: &Path| Ok(uniform.clone()),
+        )
+        .unwrap();
+        let options = search::SearchTreeOptions::from_overrides(config.search_tree_options);
+
+        let mut bytes = vec![];
+        let (count, _) = do_some_games(
+            &mut champion,
+            2,
+            &mut bytes,
+            &options,
+            &running,
+            &mut ResignationStats::default(),
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+
+        let examples = read_examples(&bytes);
+        // A game of Tic Tac Toe takes 5 to 9 moves
+        assert!(examples.len() >= 10 && examples.len() <= 18);
+        assert_eq!(
+            examples[0]["game"].get_bytes_list().get_value()[0],
+            vec![0; 19]
+        );
+        for features in &examples {
+            assert_eq!(features["game"].get_bytes_list().get_value()[0].len(), 19);
+            assert_eq!(features["choice"].get_float_list().get_value().len(), 9);
+            assert_eq!(
+                features["model_version"].get_bytes_list().get_value(),
+                &[b"3".to_vec()]
+            );
+        }
+    }
+
+    #[test]
+    fn cheap_searches_are_not_recorded() {
+        let data = seraphim_data("cheap");
+        let running = AtomicBool::new(true);
+        let uniform = Uniform {
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let mut champion = ReloadingInference::new(
+            reload::champion_dir(data.to_str().unwrap(), "m"),
+            move |_: &Path| Ok(uniform.clone()),
+        )
+        .unwrap();
+        let mut overrides = config(&data).search_tree_options;
+        overrides.full_search_fraction = Some(0.0);
+        overrides.cheap_readouts = Some(4);
+        let options = search::SearchTreeOptions::from_overrides(overrides);
+
+        let mut bytes = vec![];
+        let (count, _) = do_some_games(
+            &mut champion,
+            2,
+            &mut bytes,
+            &options,
+            &running,
+            &mut ResignationStats::default(),
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+        assert!(read_examples(&bytes).is_empty());
+    }
+
+    #[test]
+    fn only_games_picked_to_play_out_count_as_resignation_disabled() {
+        let data = seraphim_data("resign");
+        let running = AtomicBool::new(true);
+        let uniform = Uniform {
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let mut champion = ReloadingInference::new(
+            reload::champion_dir(data.to_str().unwrap(), "m"),
+            move |_: &Path| Ok(uniform.clone()),
+        )
+        .unwrap();
+        let mut overrides = config(&data).search_tree_options;
+        let mut games = |overrides: &search::SearchTreeParamOverrides| {
+            let options = search::SearchTreeOptions::from_overrides(overrides.clone());
+            let mut resignations = ResignationStats::default();
+            do_some_games(
+                &mut champion,
+                2,
+                io::sink(),
+                &options,
+                &running,
+                &mut resignations,
+            )
+            .unwrap();
+            resignations
+        };
+
+        // Without a threshold no game resigns, but none had resignation disabled either
+        let resignations = games(&overrides);
+        assert_eq!(resignations.games, 2);
+        assert_eq!(resignations.disabled_games, 0);
+
+        overrides.resign_threshold = Some(-0.9);
+        overrides.resign_disabled_fraction = Some(1.0);
+        let resignations = games(&overrides);
+        assert_eq!(resignations.games, 2);
+        assert_eq!(resignations.resignations, 0);
+        assert_eq!(resignations.disabled_games, 2);
+    }
+
+    #[test]
+    fn run_writes_tfrecords_until_it_is_stopped() {
+        let data = seraphim_data("run");
+        let running = Arc::new(AtomicBool::new(true));
+        let uniform = Uniform {
+            running: running.clone(),
+        };
+        let generator = GameGenerator::new(config(&data));
+        let resignations = generator
+            .run(move |_: &Path| Ok(uniform.clone()), &running)
+            .unwrap();
+        assert_eq!(resignations.games, 1);
+        // The champion's inferences went through the generator's cache
+        assert!(generator.cache().misses() > 0);
+        assert!(!generator.cache().is_empty());
+
+        let output_dir = data.join("gamedata").join("m");
+        let bytes = std::fs::read(output_dir.join("batch-0000000.tfrecord")).unwrap();
+        assert!(read_examples(&bytes).len() >= 5);
+        assert!(!output_dir.join("batch-0000001.tfrecord").exists());
+        assert_eq!(
+            std::fs::read_to_string(output_dir.join("control")).unwrap(),
+            "1"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Batches the inferences of many concurrent searches into single sessions.
+//! Each search owns a cheap, cloneable `BatchedInference` handle. A collector thread owns the
+//! engine, waits for up to `max_batch_size` inferences or until `timeout` elapses, runs them
+//! together through `Inference::infer_batch`, and sends each caller back its own Priors.
+use std::{sync::mpsc, thread, time};
+
+use crate::error::{InferenceError, Result};
+#[cfg(feature = "tensorflow")]
+use crate::inference::TensorFlowInferenceEngine;
+use crate::inference::{Inference, Priors};
+
+#[derive(Debug)]
+struct InferenceRequest {
+    features: Vec<u8>,
+    reply: mpsc::Sender<Result<Priors>>,
+}
+
+// A handle to a running InferenceBatcher. Every clone submits to the same batcher.
+// The batcher shuts down when the last handle is dropped.
+#[derive(Debug, Clone)]
+pub struct BatchedInference {
+    requests: mpsc::Sender<InferenceRequest>,
+}
+
+impl BatchedInference {
+    fn submit(&self, input: &[u8]) -> Result<mpsc::Receiver<Result<Priors>>> {
+        let (reply, response) = mpsc::channel();
+        self.requests
+            .send(InferenceRequest {
+                features: input.to_vec(),
+                reply,
+            })
+            .map_err(|_| InferenceError::BatcherDisconnected)?;
+        Ok(response)
+    }
+}
+
+impl Inference for BatchedInference {
+    fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+        self.submit(input)?
+            .recv()
+            .map_err(|_| InferenceError::BatcherDisconnected)?
+    }
+
+    // Every input is submitted before any reply is awaited, so they can share a batch
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> Result<Vec<Priors>> {
+        let responses = inputs
+            .iter()
+            .map(|input| self.submit(input))
+            .collect::<Result<Vec<_>>>()?;
+        responses
+            .into_iter()
+            .map(|response| {
+                response
+                    .recv()
+                    .map_err(|_| InferenceError::BatcherDisconnected)?
+            })
+            .collect()
+    }
+
+    fn try_clone(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+pub struct InferenceBatcher<I: Inference> {
+    engine: I,
+    max_batch_size: usize,
+    timeout: time::Duration,
+    requests: mpsc::Receiver<InferenceRequest>,
+}
+
+#[cfg(feature = "tensorflow")]
+impl InferenceBatcher<TensorFlowInferenceEngine> {
+    // Load the engine on a new collector thread, using the engine's own max_batch_size and timeout.
+    // A tf::Session can't be sent between threads, so the engine has to be loaded where it will run.
+    pub fn spawn<L>(load: L) -> Result<BatchedInference>
+    where
+        L: FnOnce() -> Result<TensorFlowInferenceEngine> + Send + 'static,
+    {
+        Self::spawn_loading(move || {
+            let engine = load()?;
+            let max_batch_size = engine.max_batch_size();
+            let timeout = engine.timeout();
+            Ok((engine, max_batch_size, timeout))
+        })
+    }
+}
+
+impl<I: Inference + 'static> InferenceBatcher<I> {
+    // Move an engine that is already loaded onto a new collector thread
+    pub fn spawn_with(
+        engine: I,
+        max_batch_size: usize,
+        timeout: time::Duration,
+    ) -> Result<BatchedInference>
+    where
+        I: Send,
+    {
+        Self::spawn_loading(move || Ok((engine, max_batch_size, timeout)))
+    }
+
+    // Run load on the new collector thread, and wait for it so that a model which fails to load is
+    // reported to the caller rather than to the first inference.
+    fn spawn_loading<L>(load: L) -> Result<BatchedInference>
+    where
+        L: FnOnce() -> Result<(I, usize, time::Duration)> + Send + 'static,
+    {
+        let (sender, requests) = mpsc::channel();
+        let (loaded, load_result) = mpsc::sync_channel(1);
+        thread::Builder::new()
+            .name("inference-batcher".to_string())
+            .spawn(move || {
+                let (engine, max_batch_size, timeout) = match load() {
+                    Ok(loaded_engine) => {
+                        let _ = loaded.send(Ok(()));
+                        loaded_engine
+                    }
+                    Err(err) => {
+                        let _ = loaded.send(Err(err));
+                        return;
+                    }
+                };
+                let batcher = InferenceBatcher {
+                    engine,
+                    max_batch_size: std::cmp::max(max_batch_size, 1),
+                    timeout,
+                    requests,
+                };
+                batcher.run()
+            })?;
+        load_result
+            .recv()
+            .map_err(|_| InferenceError::BatcherDisconnected)??;
+        Ok(BatchedInference { requests: sender })
+    }
+
+    fn run(mut self) {
+        // Block until there's at least one inference to do, then give the rest of the batch
+        // until the timeout to show up.
+        while let Ok(first) = self.requests.recv() {
+            let deadline = time::Instant::now() + self.timeout;
+            let mut batch = vec![first];
+            while batch.len() < self.max_batch_size {
+                let now = time::Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                match self.requests.recv_timeout(deadline - now) {
+                    Ok(request) => batch.push(request),
+                    // On disconnect, flush this batch; the outer recv will then end the thread.
+                    Err(_) => break,
+                }
+            }
+            self.run_batch(batch);
+        }
+        trace!("All BatchedInference handles were dropped. Shutting down the batcher.");
+    }
+
+    fn run_batch(&mut self, batch: Vec<InferenceRequest>) {
+        trace!("Running a batch of {} inferences", batch.len());
+        let result = {
+            let inputs: Vec<&[u8]> = batch.iter().map(|r| &r.features[..]).collect();
+            self.engine.infer_batch(&inputs)
+        };
+        match result {
+            Ok(ref priors) if priors.len() != batch.len() => {
+                // Zipping would leave the callers without an answer to see a disconnected batcher
+                let expected = batch.len();
+                for request in batch {
+                    let _ = request.reply.send(Err(InferenceError::WrongPriorCount {
+                        expected,
+                        actual: priors.len(),
+                    }
+                    .into()));
+                }
+            }
+            Ok(priors) => {
+                for (request, priors) in batch.into_iter().zip(priors) {
+                    // The caller may have given up waiting; that's not the batcher's problem.
+                    let _ = request.reply.send(Ok(priors));
+                }
+            }
+            Err(err) => {
+                let msg = format!("{}", err);
+                for request in batch {
+                    let _ = request
+                        .reply
+                        .send(Err(InferenceError::BatchFailed { msg: msg.clone() }.into()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers each input with its first byte as q, and remembers the size of every batch it ran
+    #[derive(Debug)]
+    struct Echo {
+        batches: mpsc::Sender<usize>,
+    }
+
+    impl Inference for Echo {
+        fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+            Ok(Priors {
+                ps: vec![],
+                q: f32::from(input[0]),
+            })
+        }
+
+        fn infer_batch(&mut self, inputs: &[&[u8]]) -> Result<Vec<Priors>> {
+            let _ = self.batches.send(inputs.len());
+            inputs.iter().map(|input| self.infer(input)).collect()
+        }
+    }
+
+    #[test]
+    fn each_caller_gets_its_own_priors_from_one_batch() {
+        let (batches, sizes) = mpsc::channel();
+        let mut handle =
+            InferenceBatcher::spawn_with(Echo { batches }, 4, time::Duration::from_secs(5))
+                .unwrap();
+        let priors = handle.infer_batch(&[&[1], &[2], &[3], &[4]]).unwrap();
+        let qs: Vec<f32> = priors.iter().map(|priors| priors.q).collect();
+        assert_eq!(qs, vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(sizes.recv().unwrap(), 4);
+    }
+
+    #[test]
+    fn a_failed_load_is_returned_by_spawn() {
+        let spawned =
+            InferenceBatcher::<Echo>::spawn_loading(|| Err(failure::err_msg("no saved model")));
+        assert_eq!(format!("{}", spawned.unwrap_err()), "no saved model");
+    }
+}
@@ -0,0 +1,276 @@
+//! Memoizes inferences by the feature bytes of the state they were made for.
+//! Self-play reaches the same positions over and over, especially in the opening, and every
+//! repeat would otherwise cost a session run. The cache is bounded, and evicts the least recently
+//! used Priors once it's full.
+//!
+//! An `InferenceCache` is a shared handle, so one cache can outlive any single search: give a clone
+//! of it to the `CachingInference` of every game played in a process, and later games start with
+//! the positions that earlier games already paid for.
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use crate::error::{InferenceError, Result};
+use crate::inference::{Inference, Priors};
+
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    entries: HashMap<Vec<u8>, (Priors, u64)>, // The Priors and the tick they were last used at
+    recency: BTreeMap<u64, Vec<u8>>,          // Keys by the tick they were last used at
+    tick: u64,
+    hits: usize,
+    misses: usize,
+}
+
+impl Lru {
+    fn get(&mut self, features: &[u8]) -> Option<Priors> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(features) {
+            Some((priors, last_used)) => {
+                let key = self.recency.remove(last_used).unwrap();
+                self.recency.insert(tick, key);
+                *last_used = tick;
+                self.hits += 1;
+                Some(priors.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, features: &[u8], priors: Priors) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.remove(features) {
+            // Another search inferred the same state while we were waiting on ours
+            self.recency.remove(&last_used);
+        }
+        while self.entries.len() >= self.capacity {
+            let oldest = *self.recency.keys().next().unwrap();
+            let key = self.recency.remove(&oldest).unwrap();
+            self.entries.remove(&key);
+        }
+        self.recency.insert(self.tick, features.to_vec());
+        self.entries.insert(features.to_vec(), (priors, self.tick));
+    }
+}
+
+// A bounded LRU cache of Priors. Every clone is a handle to the same cache.
+#[derive(Debug, Clone)]
+pub struct InferenceCache {
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl InferenceCache {
+    // A cache that holds the Priors of up to capacity states
+    pub fn new(capacity: usize) -> Self {
+        InferenceCache {
+            lru: Arc::new(Mutex::new(Lru {
+                capacity,
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+                hits: 0,
+                misses: 0,
+            })),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lru.lock().unwrap().capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> usize {
+        self.lru.lock().unwrap().hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.lru.lock().unwrap().misses
+    }
+
+    // Forget every cached inference, e.g. after the model has changed. The counters are kept.
+    pub fn clear(&self) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.entries.clear();
+        lru.recency.clear();
+    }
+
+    fn get(&self, features: &[u8]) -> Option<Priors> {
+        self.lru.lock().unwrap().get(features)
+    }
+
+    fn insert(&self, features: &[u8], priors: Priors) {
+        self.lru.lock().unwrap().insert(features, priors)
+    }
+}
+
+// An Inference that only asks the engine it wraps about states that aren't in its cache
+#[derive(Debug)]
+pub struct CachingInference<I: Inference> {
+    inner: I,
+    cache: InferenceCache,
+}
+
+impl<I: Inference> CachingInference<I> {
+    // Wrap inner with a new cache of the given capacity
+    pub fn new(inner: I, capacity: usize) -> Self {
+        Self::with_cache(inner, InferenceCache::new(capacity))
+    }
+
+    // Wrap inner with an existing cache, shared with every other CachingInference that holds it
+    pub fn with_cache(inner: I, cache: InferenceCache) -> Self {
+        CachingInference { inner, cache }
+    }
+
+    pub fn cache(&self) -> &InferenceCache {
+        &self.cache
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Inference> Inference for CachingInference<I> {
+    fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+        if let Some(priors) = self.cache.get(input) {
+            return Ok(priors);
+        }
+        // The cache isn't locked while the engine runs, so concurrent searches aren't serialized.
+        let priors = self.inner.infer(input)?;
+        self.cache.insert(input, priors.clone());
+        Ok(priors)
+    }
+
+    // Only the inputs that miss the cache are passed on to the engine, as one batch
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> Result<Vec<Priors>> {
+        let mut priors: Vec<Option<Priors>> =
+            inputs.iter().map(|input| self.cache.get(input)).collect();
+        let misses: Vec<&[u8]> = inputs
+            .iter()
+            .zip(&priors)
+            .filter(|(_, priors)| priors.is_none())
+            .map(|(input, _)| *input)
+            .collect();
+        if !misses.is_empty() {
+            let mut engine_priors = self.inner.infer_batch(&misses)?.into_iter();
+            for (input, slot) in inputs.iter().zip(priors.iter_mut()) {
+                if slot.is_none() {
+                    let inferred =
+                        engine_priors
+                            .next()
+                            .ok_or_else(|| InferenceError::BatchFailed {
+                                msg: format!(
+                                    "Expected {} inferences, but got fewer.",
+                                    misses.len()
+                                ),
+                            })?;
+                    self.cache.insert(input, inferred.clone());
+                    *slot = Some(inferred);
+                }
+            }
+        }
+        Ok(priors.into_iter().map(Option::unwrap).collect())
+    }
+
+    fn try_clone(&self) -> Option<Self> {
+        self.inner.try_clone().map(|inner| CachingInference {
+            inner,
+            cache: self.cache.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers with the first byte of the input as q, and counts how often it was asked
+    #[derive(Debug, Default)]
+    struct Counting {
+        inferences: usize,
+    }
+
+    impl Inference for Counting {
+        fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+            self.inferences += 1;
+            Ok(Priors {
+                ps: vec![],
+                q: f32::from(input[0]),
+            })
+        }
+    }
+
+    fn priors(q: f32) -> Priors {
+        Priors { ps: vec![], q }
+    }
+
+    #[test]
+    fn the_least_recently_used_state_is_evicted() {
+        let cache = InferenceCache::new(2);
+        cache.insert(&[1], priors(1.0));
+        cache.insert(&[2], priors(2.0));
+        // Using 1 makes 2 the oldest
+        assert_eq!(cache.get(&[1]).unwrap().q, 1.0);
+        cache.insert(&[3], priors(3.0));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&[2]).is_none());
+        assert_eq!(cache.get(&[1]).unwrap().q, 1.0);
+        assert_eq!(cache.get(&[3]).unwrap().q, 3.0);
+
+        // Reinserting a state refreshes it rather than taking a second slot
+        cache.insert(&[1], priors(1.5));
+        cache.insert(&[4], priors(4.0));
+        assert!(cache.get(&[3]).is_none());
+        assert_eq!(cache.get(&[1]).unwrap().q, 1.5);
+
+        let disabled = InferenceCache::new(0);
+        disabled.insert(&[1], priors(1.0));
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn only_misses_reach_the_engine() {
+        let mut caching = CachingInference::new(Counting::default(), 8);
+        assert_eq!(caching.infer(&[1]).unwrap().q, 1.0);
+        assert_eq!(caching.infer(&[1]).unwrap().q, 1.0);
+        let qs: Vec<f32> = caching
+            .infer_batch(&[&[1], &[2], &[3], &[2]])
+            .unwrap()
+            .iter()
+            .map(|priors| priors.q)
+            .collect();
+        assert_eq!(qs, vec![1.0, 2.0, 3.0, 2.0]);
+        // The batch's misses are looked up before any of them are inferred
+        assert_eq!((caching.cache().hits(), caching.cache().misses()), (2, 4));
+        assert_eq!(caching.into_inner().inferences, 4);
+    }
+
+    #[test]
+    fn clearing_keeps_the_counters() {
+        let cache = InferenceCache::new(2);
+        let mut caching = CachingInference::with_cache(Counting::default(), cache.clone());
+        caching.infer(&[1]).unwrap();
+        caching.infer(&[1]).unwrap();
+        cache.clear();
+        assert!(caching.cache().is_empty());
+        caching.infer(&[1]).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(caching.into_inner().inferences, 2);
+    }
+}
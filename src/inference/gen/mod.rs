@@ -0,0 +1,2 @@
+pub mod meta_graph;
+pub mod saved_model;
@@ -0,0 +1,44 @@
+pub mod batch;
+pub mod cache;
+mod gen;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod reload;
+pub mod signature;
+#[cfg(feature = "tensorflow")]
+pub mod tf;
+
+#[cfg(feature = "onnx")]
+pub use self::onnx::OnnxInferenceEngine;
+#[cfg(feature = "tensorflow")]
+pub use self::tf::TensorFlowInferenceEngine;
+
+#[derive(Debug, Clone)]
+pub struct Priors {
+    pub ps: Vec<f32>,
+    pub q: f32,
+}
+
+// The first float is expected to be the qvalue for the position
+// The remaining floats are the ps for each move.error
+// This method must return exactly game.max_actions() + 1 f32s.
+// Engines needn't be Send, e.g. a TensorFlow session isn't. Searching runs readouts on worker threads,
+// so a SearchTree's engine must be; others can be shared through batch::InferenceBatcher.
+pub trait Inference: std::fmt::Debug {
+    fn infer(&mut self, input: &[u8]) -> crate::error::Result<Priors>;
+
+    // Infer several states at once. The priors are returned in the same order as the inputs.
+    // Engines that can evaluate a batch in one pass, like a neural network, should override this.
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> crate::error::Result<Vec<Priors>> {
+        inputs.iter().map(|input| self.infer(input)).collect()
+    }
+
+    // Another handle to this engine that can run inferences concurrently with this one,
+    // e.g. for the worker threads of a parallel search. Engines that can't be shared return None.
+    fn try_clone(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
@@ -0,0 +1,174 @@
+//! Runs an ONNX export of the policy and value network on the CPU with tract, a pure-Rust runtime,
+//! so that seraphim can be built without libtensorflow. Enabled by the "onnx" feature.
+//! The model's first input takes a [n, feature length] tensor of the states' feature bytes, cast to
+//! whatever type the model declares for it. Its outputs are named like those of a SavedModel, by a
+//! `ModelOutputs`: either one packed [n, 1 + action count] output, or separate policy and value outputs.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tract_onnx::prelude::*;
+use tract_onnx::tract_hir::infer::Factoid;
+use tract_onnx::tract_hir::internal::DimLike;
+
+use crate::error::{InferenceError, ModelError, Result};
+use crate::inference::signature::ModelOutputs;
+use crate::inference::{Inference, Priors};
+
+type Plan = TypedRunnableModel<TypedModel>;
+
+// Clones share the optimized model, so every worker of a parallel search can run it concurrently
+#[derive(Debug, Clone)]
+pub struct OnnxInferenceEngine {
+    plan: Arc<Plan>,
+    input_type: DatumType,
+    feature_len: usize,
+    outputs: ModelOutputs,
+    path: PathBuf,
+    action_count: usize, // How many priors the model returns for each state
+}
+
+impl OnnxInferenceEngine {
+    // Load and optimize the model in the .onnx file at p. The batch dimension of its input is left
+    // symbolic, so that the one optimized plan runs batches of any size.
+    pub fn from_path<P>(p: P, outputs: &ModelOutputs, action_count: usize) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        trace!("Attemping to load ONNX model from '{:?}'", p.as_ref());
+        let path = p.as_ref().to_owned();
+        let couldnt_load = |err: TractError| ModelError::CouldntLoadOnnx {
+            path: path.clone(),
+            msg: format!("{:?}", err),
+        };
+        let names: Vec<&str> = match outputs {
+            ModelOutputs::Packed(output) => vec![output],
+            ModelOutputs::Separate { policy, value } => vec![policy, value],
+        };
+        let model = tract_onnx::onnx()
+            .model_for_path(&path)
+            .and_then(|model| model.with_output_names(names))
+            .map_err(couldnt_load)?;
+
+        let input = model.input_fact(0).map_err(couldnt_load)?;
+        let input_type = input.datum_type.concretize().unwrap_or(DatumType::U8);
+        let feature_len = input
+            .shape
+            .dim(1)
+            .and_then(|dim| dim.concretize())
+            .and_then(|dim| dim.to_usize().ok())
+            .ok_or_else(|| ModelError::CouldntLoadOnnx {
+                path: path.clone(),
+                msg: format!(
+                    "The input must have a fixed feature length, but its shape is {:?}",
+                    input.shape
+                ),
+            })?;
+        let batch = model.symbols.sym("N");
+        let plan = model
+            .with_input_fact(
+                0,
+                InferenceFact::dt_shape(
+                    input_type,
+                    tvec!(TDim::from(batch), TDim::from(feature_len)),
+                ),
+            )
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(couldnt_load)?;
+        trace!("...success.");
+
+        Ok(OnnxInferenceEngine {
+            plan: Arc::new(plan),
+            input_type,
+            feature_len,
+            outputs: outputs.clone(),
+            path,
+            action_count,
+        })
+    }
+
+    // Check that output returned expected floats for the batch
+    fn check_output_length(&self, output: &str, expected: usize, actual: usize) -> Result<()> {
+        if actual != expected {
+            return Err(InferenceError::WrongOutputLength {
+                op: output.to_string(),
+                dir: self.path.clone(),
+                expected,
+                actual,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl Inference for OnnxInferenceEngine {
+    fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+        let mut priors = self.infer_batch(&[input])?;
+        Ok(priors.remove(0))
+    }
+
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> Result<Vec<Priors>> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut values = Vec::with_capacity(inputs.len() * self.feature_len);
+        for input in inputs {
+            if input.len() != self.feature_len {
+                return Err(InferenceError::RaggedBatch {
+                    expected: self.feature_len,
+                    actual: input.len(),
+                }
+                .into());
+            }
+            values.extend_from_slice(input);
+        }
+        let shape = vec![inputs.len(), self.feature_len];
+        let failed = |err: TractError| InferenceError::OnnxFailed {
+            path: self.path.clone(),
+            shape: shape.clone(),
+            msg: format!("{:?}", err),
+        };
+        let tensor = Tensor::from_shape(&shape, &values)
+            .and_then(|tensor| Ok(tensor.cast_to_dt(self.input_type)?.into_owned()))
+            .map_err(failed)?;
+        let results = self.plan.run(tvec!(tensor.into())).map_err(failed)?;
+        let floats = |ix: usize| -> Result<Vec<f32>> {
+            let output = results[ix].cast_to::<f32>().map_err(failed)?;
+            Ok(output.as_slice::<f32>().map_err(failed)?.to_vec())
+        };
+
+        match self.outputs {
+            ModelOutputs::Packed(ref output) => {
+                // Each row of the output is [q, ps...] for the corresponding input
+                let packed = floats(0)?;
+                let row_len = self.action_count + 1;
+                self.check_output_length(output, inputs.len() * row_len, packed.len())?;
+                Ok(packed
+                    .chunks(row_len)
+                    .map(|row| Priors {
+                        ps: row[1..].to_vec(),
+                        q: row[0],
+                    })
+                    .collect())
+            }
+            ModelOutputs::Separate {
+                ref policy,
+                ref value,
+            } => {
+                let ps = floats(0)?;
+                let qs = floats(1)?;
+                self.check_output_length(policy, inputs.len() * self.action_count, ps.len())?;
+                self.check_output_length(value, inputs.len(), qs.len())?;
+                Ok(ps
+                    .chunks(self.action_count)
+                    .zip(qs)
+                    .map(|(ps, q)| Priors { ps: ps.to_vec(), q })
+                    .collect())
+            }
+        }
+    }
+
+    fn try_clone(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
@@ -0,0 +1,234 @@
+//! Keeps a long-running game generator playing with the current champion. train.py promotes a new
+//! champion by rewriting $SERAPHIM_DATA/models/<name>/champion/saved_model while it holds an exclusive
+//! lock on champion/lock. A `ReloadingInference` checks for a new champion between games, under a
+//! shared lock on the same file, and loads it for the games that follow. Games already in progress
+//! keep the model they started with.
+//!
+//! The champion's version is the contents of champion/version, which train.py writes with the
+//! SavedModel, or else the modification time of its saved_model.pb.
+//!
+//! Inferences cached for one champion are no good for the next, so a cache that the loaded engines
+//! share can be handed to `clearing_on_reload`, which empties it whenever a new champion is loaded.
+use fs2::FileExt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time;
+
+use crate::error::{InferenceError, ModelError, Result};
+use crate::inference::{cache::InferenceCache, Inference, Priors};
+
+// Loads the engine for the SavedModel in a directory, e.g. TensorFlowInferenceEngine::from_saved_model
+// wrapped in an InferenceBatcher, so that it can be shared between games
+type Loader<I> = dyn Fn(&Path) -> Result<I> + Send + Sync;
+
+pub struct ReloadingInference<I: Inference> {
+    champion_dir: PathBuf,
+    load: Arc<Loader<I>>,
+    inner: I,
+    version: String, // The version of the champion that inner was loaded from
+    cache: Option<InferenceCache>, // Cleared whenever a new champion is loaded
+}
+
+// The directory that holds the champion of the model called model_name
+pub fn champion_dir(seraphim_data: &str, model_name: &str) -> PathBuf {
+    Path::new(seraphim_data)
+        .join("models")
+        .join(model_name)
+        .join("champion")
+}
+
+impl<I: Inference> ReloadingInference<I> {
+    // Load the current champion in champion_dir with load
+    pub fn new<P, L>(champion_dir: P, load: L) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        L: Fn(&Path) -> Result<I> + Send + Sync + 'static,
+    {
+        let champion_dir = champion_dir.as_ref().to_owned();
+        let load: Arc<Loader<I>> = Arc::new(load);
+        let (inner, version) = load_champion(&champion_dir, &*load)?;
+        info!(
+            "Loaded version {} of the champion in {:?}",
+            version, champion_dir
+        );
+        Ok(ReloadingInference {
+            champion_dir,
+            load,
+            inner,
+            version,
+            cache: None,
+        })
+    }
+
+    // Clear cache whenever a new champion is loaded, e.g. the cache that load wraps every champion in
+    // with a CachingInference
+    pub fn clearing_on_reload(mut self, cache: InferenceCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // The version of the champion that this handle infers with
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    // Call before every game. If a new champion has been promoted since the last game, switch to it.
+    // Returns a handle to give the game's SearchTree, which keeps inferring with the same model
+    // however many champions are promoted while the game is played. If the new champion can't be
+    // loaded, e.g. because it was only half written, the games go on with the old one.
+    pub fn next_game(&mut self) -> Result<Self> {
+        match self.reload_if_promoted() {
+            Ok(true) => info!(
+                "Switched to version {} of the champion in {:?}",
+                self.version, self.champion_dir
+            ),
+            Ok(false) => {}
+            Err(err) => warn!(
+                "Couldn't load the new champion in {:?}. Still playing with version {}:\n{}",
+                self.champion_dir, self.version, err
+            ),
+        }
+        let game = self
+            .try_clone()
+            .ok_or_else(|| InferenceError::NotShareable {
+                dir: self.champion_dir.clone(),
+            })?;
+        info!("Generating a game with champion version {}", self.version);
+        Ok(game)
+    }
+
+    // Load the champion if its version has changed. Returns whether it did.
+    pub fn reload_if_promoted(&mut self) -> Result<bool> {
+        let lock = lock_champion(&self.champion_dir)?;
+        let version = champion_version(&self.champion_dir)?;
+        if version == self.version {
+            return Ok(false);
+        }
+        let inner = (self.load)(&self.champion_dir.join("saved_model"))?;
+        FileExt::unlock(&lock)?;
+        self.inner = inner;
+        self.version = version;
+        if let Some(ref cache) = self.cache {
+            cache.clear();
+        }
+        Ok(true)
+    }
+}
+
+impl<I: Inference> std::fmt::Debug for ReloadingInference<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReloadingInference")
+            .field("champion_dir", &self.champion_dir)
+            .field("inner", &self.inner)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl<I: Inference> Inference for ReloadingInference<I> {
+    fn infer(&mut self, input: &[u8]) -> Result<Priors> {
+        self.inner.infer(input)
+    }
+
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> Result<Vec<Priors>> {
+        self.inner.infer_batch(inputs)
+    }
+
+    fn try_clone(&self) -> Option<Self> {
+        self.inner.try_clone().map(|inner| ReloadingInference {
+            champion_dir: self.champion_dir.clone(),
+            load: self.load.clone(),
+            inner,
+            version: self.version.clone(),
+            cache: self.cache.clone(),
+        })
+    }
+}
+
+fn load_champion<I>(champion_dir: &Path, load: &Loader<I>) -> Result<(I, String)> {
+    let lock = lock_champion(champion_dir)?;
+    let version = champion_version(champion_dir)?;
+    let inner = load(&champion_dir.join("saved_model"))?;
+    FileExt::unlock(&lock)?;
+    Ok((inner, version))
+}
+
+// Hold a shared lock on the champion, so that it isn't promoted over while it's being read. The lock
+// is released when the file is closed, on early returns too.
+fn lock_champion(champion_dir: &Path) -> Result<fs::File> {
+    let lock = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(champion_dir.join("lock"))
+        .map_err(|io_error| ModelError::ModelNotFound {
+            name: "champion".to_string(),
+            search_dirs: vec![format!("{:?}", champion_dir)],
+            io_error,
+        })?;
+    FileExt::lock_shared(&lock)?;
+    Ok(lock)
+}
+
+fn champion_version(champion_dir: &Path) -> Result<String> {
+    match fs::read_to_string(champion_dir.join("version")) {
+        Ok(version) => Ok(version.trim().to_string()),
+        Err(_) => {
+            let modified = fs::metadata(champion_dir.join("saved_model").join("saved_model.pb"))
+                .and_then(|metadata| metadata.modified())
+                .map_err(|io_error| ModelError::ModelNotFound {
+                    name: "champion".to_string(),
+                    search_dirs: vec![format!("{:?}", champion_dir)],
+                    io_error,
+                })?;
+            let since_epoch = modified
+                .duration_since(time::UNIX_EPOCH)
+                .unwrap_or_default();
+            Ok(format!(
+                "{}.{:09}",
+                since_epoch.as_secs(),
+                since_epoch.subsec_nanos()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::cache::CachingInference;
+
+    #[derive(Debug)]
+    struct Constant;
+
+    impl Inference for Constant {
+        fn infer(&mut self, _input: &[u8]) -> Result<Priors> {
+            Ok(Priors { ps: vec![], q: 0.0 })
+        }
+    }
+
+    #[test]
+    fn promoting_a_champion_clears_the_cache() {
+        let champion_dir =
+            std::env::temp_dir().join(format!("seraphim-reload-{}", std::process::id()));
+        fs::create_dir_all(&champion_dir).unwrap();
+        fs::write(champion_dir.join("version"), "1\n").unwrap();
+
+        let cache = InferenceCache::new(8);
+        let shared = cache.clone();
+        let mut champion = ReloadingInference::new(&champion_dir, move |_: &Path| {
+            Ok(CachingInference::with_cache(Constant, shared.clone()))
+        })
+        .unwrap()
+        .clearing_on_reload(cache.clone());
+        champion.infer(&[1]).unwrap();
+        assert!(!champion.reload_if_promoted().unwrap());
+        assert_eq!(cache.len(), 1);
+
+        fs::write(champion_dir.join("version"), "2\n").unwrap();
+        assert!(champion.reload_if_promoted().unwrap());
+        assert_eq!(champion.version(), "2");
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+//! Which tensors of a SavedModel's graph are the network's input and outputs.
+//! Tensors are named either directly, as "op" or "op:index", or by their keys in one of the
+//! SignatureDefs that the SavedModel was exported with, e.g. "serving_default" for a model saved
+//! with `tf.saved_model.simple_save`. SignatureDefs are looked up in the SavedModel's own metadata,
+//! saved_model.pb, so a model can be loaded without knowing how its graph's ops were named.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{ModelError, Result};
+use crate::inference::gen::{meta_graph::TensorInfo, saved_model::SavedModel};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelSignature {
+    pub tags: Vec<String>, // Selects the MetaGraphDef to load
+    // When set, input and the names in outputs are keys of this SignatureDef's inputs and outputs
+    // rather than tensor names
+    pub signature_def: Option<String>,
+    pub input: String, // A [n, feature length] u8 tensor of the states' feature bytes
+    pub outputs: ModelOutputs,
+    // A bool placeholder that's fed false, for graphs that switch e.g. batch norm on it. Always a
+    // tensor name, and optional: a graph without it is fine.
+    pub training: Option<String>,
+}
+impl std::default::Default for ModelSignature {
+    // The ops that src/tictactoe/train.py names
+    fn default() -> Self {
+        Self {
+            tags: vec!["serve".to_string()],
+            signature_def: None,
+            input: "example".to_string(),
+            outputs: ModelOutputs::Packed("softmax".to_string()),
+            training: Some("training".to_string()),
+        }
+    }
+}
+
+impl ModelSignature {
+    // Look the tensors up by their keys in the "serving_default" SignatureDef, as written by
+    // tf.saved_model.simple_save
+    pub fn serving_default(input: &str, outputs: ModelOutputs) -> Self {
+        Self {
+            signature_def: Some("serving_default".to_string()),
+            input: input.to_string(),
+            outputs,
+            training: None,
+            ..Default::default()
+        }
+    }
+
+    // The tensor names this signature refers to, resolving the SignatureDef keys against the metadata
+    // of the SavedModel in dir if there's a signature_def
+    pub fn resolve<P: AsRef<Path>>(&self, dir: P) -> Result<ResolvedSignature> {
+        let (input, outputs) = match self.signature_def {
+            None => (self.input.clone(), self.outputs.clone()),
+            Some(ref key) => {
+                let signature = read_signature_def(dir.as_ref(), &self.tags, key)?;
+                let lookup = |tensors: &HashMap<String, String>, name: &str| {
+                    tensors
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| ModelError::SignatureKeyNotFound {
+                            key: name.to_string(),
+                            signature_def: key.clone(),
+                            dir: dir.as_ref().to_owned(),
+                        })
+                };
+                let input = lookup(&signature.inputs, &self.input)?;
+                let outputs = match self.outputs {
+                    ModelOutputs::Packed(ref output) => {
+                        ModelOutputs::Packed(lookup(&signature.outputs, output)?)
+                    }
+                    ModelOutputs::Separate {
+                        ref policy,
+                        ref value,
+                    } => ModelOutputs::Separate {
+                        policy: lookup(&signature.outputs, policy)?,
+                        value: lookup(&signature.outputs, value)?,
+                    },
+                };
+                (input, outputs)
+            }
+        };
+        Ok(ResolvedSignature {
+            input: TensorName::parse(&input),
+            outputs: match outputs {
+                ModelOutputs::Packed(output) => ResolvedOutputs::Packed(TensorName::parse(&output)),
+                ModelOutputs::Separate { policy, value } => ResolvedOutputs::Separate {
+                    policy: TensorName::parse(&policy),
+                    value: TensorName::parse(&value),
+                },
+            },
+            training: self.training.as_ref().map(|name| TensorName::parse(name)),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ModelOutputs {
+    // One [n, 1 + action count] tensor whose rows are [q, ps...]
+    Packed(String),
+    // A [n, action count] tensor of ps, and a [n] or [n, 1] tensor of qs
+    Separate { policy: String, value: String },
+}
+
+// An output of one of the graph's ops
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorName {
+    pub op: String,
+    pub index: i32,
+}
+
+impl TensorName {
+    // "op:index", or "op" for the op's first output
+    pub fn parse(name: &str) -> Self {
+        match name.rfind(':') {
+            Some(colon) => match name[colon + 1..].parse() {
+                Ok(index) => TensorName {
+                    op: name[..colon].to_string(),
+                    index,
+                },
+                Err(_) => TensorName {
+                    op: name.to_string(),
+                    index: 0,
+                },
+            },
+            None => TensorName {
+                op: name.to_string(),
+                index: 0,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for TensorName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.op, self.index)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedSignature {
+    pub input: TensorName,
+    pub outputs: ResolvedOutputs,
+    pub training: Option<TensorName>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolvedOutputs {
+    Packed(TensorName),
+    Separate {
+        policy: TensorName,
+        value: TensorName,
+    },
+}
+
+// The tensor names of a SignatureDef's inputs and outputs, by key
+#[derive(Debug, Default)]
+struct SignatureDef {
+    inputs: HashMap<String, String>,
+    outputs: HashMap<String, String>,
+}
+
+// Find the SignatureDef called key in the MetaGraphDef with exactly the given tags, as TensorFlow's
+// loader matches them, in dir/saved_model.pb.
+fn read_signature_def(dir: &Path, tags: &[String], key: &str) -> Result<SignatureDef> {
+    let path = dir.join("saved_model.pb");
+    let bytes = std::fs::read(&path).map_err(|io_error| ModelError::CouldntReadMetadata {
+        path: path.clone(),
+        io_error,
+    })?;
+    let saved_model: SavedModel = protobuf::parse_from_bytes(&bytes).map_err(|protobuf_error| {
+        ModelError::MalformedMetadata {
+            path: path.clone(),
+            protobuf_error,
+        }
+    })?;
+    let mut tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+    tags.sort_unstable();
+    tags.dedup();
+
+    for meta_graph in saved_model.get_meta_graphs() {
+        let mut meta_graph_tags: Vec<&str> = meta_graph
+            .get_meta_info_def()
+            .get_tags()
+            .iter()
+            .map(String::as_str)
+            .collect();
+        meta_graph_tags.sort_unstable();
+        meta_graph_tags.dedup();
+        if meta_graph_tags != tags {
+            continue;
+        }
+        if let Some(signature) = meta_graph.get_signature_def().get(key) {
+            let names = |tensors: &HashMap<String, TensorInfo>| {
+                tensors
+                    .iter()
+                    .map(|(key, info)| (key.clone(), info.get_name().to_string()))
+                    .collect()
+            };
+            return Ok(SignatureDef {
+                inputs: names(signature.get_inputs()),
+                outputs: names(signature.get_outputs()),
+            });
+        }
+    }
+    Err(ModelError::SignatureDefNotFound {
+        signature_def: key.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        dir: dir.to_owned(),
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::gen::meta_graph;
+    use protobuf::Message;
+    use std::path::PathBuf;
+
+    // A MetaGraphDef whose SignatureDef "serving_default" maps the keys "x" and "y" to the tensors
+    // "in:0" and "out:0"
+    fn meta_graph(tags: &[&str]) -> meta_graph::MetaGraphDef {
+        let tensor_info = |name: &str| {
+            let mut info = TensorInfo::new();
+            info.set_name(name.to_string());
+            info
+        };
+        let mut signature = meta_graph::SignatureDef::new();
+        signature
+            .mut_inputs()
+            .insert("x".to_string(), tensor_info("in:0"));
+        signature
+            .mut_outputs()
+            .insert("y".to_string(), tensor_info("out:0"));
+        let mut meta_graph = meta_graph::MetaGraphDef::new();
+        for tag in tags {
+            meta_graph
+                .mut_meta_info_def()
+                .mut_tags()
+                .push(tag.to_string());
+        }
+        meta_graph
+            .mut_signature_def()
+            .insert("serving_default".to_string(), signature);
+        meta_graph
+    }
+
+    fn saved_model(meta_graphs: Vec<meta_graph::MetaGraphDef>) -> Vec<u8> {
+        let mut saved_model = SavedModel::new();
+        saved_model.set_saved_model_schema_version(1);
+        saved_model.set_meta_graphs(protobuf::RepeatedField::from_vec(meta_graphs));
+        saved_model.write_to_bytes().unwrap()
+    }
+
+    fn write_saved_model(name: &str, bytes: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "seraphim-signature-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("saved_model.pb"), bytes).unwrap();
+        dir
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_signature_defs() {
+        let dir = write_saved_model("reads", &saved_model(vec![meta_graph(&["serve"])]));
+        let signature = read_signature_def(&dir, &tags(&["serve"]), "serving_default").unwrap();
+        assert_eq!(signature.inputs["x"], "in:0");
+        assert_eq!(signature.outputs["y"], "out:0");
+    }
+
+    #[test]
+    fn tags_must_match_exactly() {
+        let dir = write_saved_model("tags", &saved_model(vec![meta_graph(&["serve", "gpu"])]));
+        assert!(read_signature_def(&dir, &tags(&["serve"]), "serving_default").is_err());
+        assert!(read_signature_def(&dir, &tags(&["gpu", "serve"]), "serving_default").is_ok());
+        assert!(
+            read_signature_def(&dir, &tags(&["serve", "gpu", "train"]), "serving_default").is_err()
+        );
+    }
+
+    #[test]
+    fn the_meta_graph_with_the_tags_is_used() {
+        let mut training = meta_graph(&["train"]);
+        training.mut_signature_def().clear();
+        let dir = write_saved_model(
+            "meta-graphs",
+            &saved_model(vec![training, meta_graph(&["serve"])]),
+        );
+        assert!(read_signature_def(&dir, &tags(&["serve"]), "serving_default").is_ok());
+        assert!(read_signature_def(&dir, &tags(&["train"]), "serving_default").is_err());
+    }
+
+    #[test]
+    fn missing_signature_defs_are_reported() {
+        let dir = write_saved_model("missing", &saved_model(vec![meta_graph(&["serve"])]));
+        let err = read_signature_def(&dir, &tags(&["serve"]), "predict").unwrap_err();
+        match err.downcast::<ModelError>() {
+            Ok(ModelError::SignatureDefNotFound { signature_def, .. }) => {
+                assert_eq!(signature_def, "predict")
+            }
+            other => panic!("Expected SignatureDefNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_metadata_is_malformed() {
+        let mut bytes = saved_model(vec![meta_graph(&["serve"])]);
+        bytes.truncate(bytes.len() / 2);
+        let dir = write_saved_model("truncated", &bytes);
+        let err = read_signature_def(&dir, &tags(&["serve"]), "serving_default").unwrap_err();
+        match err.downcast::<ModelError>() {
+            Ok(ModelError::MalformedMetadata { .. }) => {}
+            other => panic!("Expected MalformedMetadata, got {:?}", other),
+        }
+    }
+
+    // A real MetaGraphDef also holds the graph and much more, which meta_graph.proto leaves out
+    #[test]
+    fn fields_that_arent_read_are_skipped() {
+        let mut serve = meta_graph(&["serve"]);
+        // GraphDef graph_def = 2 { repeated NodeDef node = 1 { string name = 1; } }
+        serve
+            .mut_unknown_fields()
+            .add_length_delimited(2, b"\x0a\x04\x0a\x02in".to_vec());
+        // SaverDef saver_def = 3 { string filename_tensor_name = 1; }
+        serve
+            .mut_unknown_fields()
+            .add_length_delimited(3, b"\x0a\x06save:0".to_vec());
+        let dir = write_saved_model("unknown", &saved_model(vec![serve]));
+        let signature = read_signature_def(&dir, &tags(&["serve"]), "serving_default").unwrap();
+        assert_eq!(signature.inputs["x"], "in:0");
+    }
+}
@@ -0,0 +1,239 @@
+//! Runs a TensorFlow SavedModel through libtensorflow. Enabled by the "tensorflow" feature, which is on by default.
+use crate::error::{InferenceError, ModelError, TensorflowError};
+use crate::inference::signature::{ModelSignature, ResolvedOutputs, TensorName};
+use crate::inference::{Inference, Priors};
+use failure::ResultExt;
+use tensorflow as tf;
+
+// A type which does inference via Tensorflow Rust bindings
+#[derive(Debug)]
+pub struct TensorFlowInferenceEngine {
+    session: tf::Session,
+    example: GraphTensor,
+    outputs: GraphOutputs,
+    graph: tf::Graph,
+    training: Option<GraphTensor>,
+    timeout: std::time::Duration,
+    max_batch_size: usize,
+    training_tensor: tf::Tensor<bool>,
+    model_dir: std::path::PathBuf,
+    action_count: usize, // How many priors the model returns for each state
+}
+
+// An op of the loaded graph, and which of its outputs to feed or fetch
+#[derive(Debug)]
+struct GraphTensor {
+    name: TensorName,
+    op: tf::Operation,
+}
+
+#[derive(Debug)]
+enum GraphOutputs {
+    Packed(GraphTensor),
+    Separate {
+        policy: GraphTensor,
+        value: GraphTensor,
+    },
+}
+
+impl GraphOutputs {
+    fn names(&self) -> String {
+        match self {
+            GraphOutputs::Packed(output) => output.name.to_string(),
+            GraphOutputs::Separate { policy, value } => format!("{}, {}", policy.name, value.name),
+        }
+    }
+}
+
+impl TensorFlowInferenceEngine {
+    // Load the MetaGraphDef with signature's tags from the SavedModel in directory p, and find the
+    // tensors that signature names in it
+    pub fn from_saved_model<P>(
+        p: P,
+        signature: &ModelSignature,
+        action_count: usize,
+        max_batch_size: usize,
+        timeout: std::time::Duration,
+    ) -> crate::error::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        trace!("Attemping to load saved model from '{:?}'", p.as_ref());
+        let resolved = signature.resolve(p.as_ref())?;
+        let mut graph = tf::Graph::new();
+
+        let session = tf::Session::from_saved_model(
+            &tf::SessionOptions::new(),
+            &signature.tags,
+            &mut graph,
+            p.as_ref(),
+        )
+        .map_err(|tf| ModelError::CouldntLoad {
+            dir: p.as_ref().to_owned(),
+            tf: tf.into(),
+        })?;
+        trace!("...success.");
+
+        let example = GraphTensor::required(&graph, resolved.input)?;
+        let outputs = match resolved.outputs {
+            ResolvedOutputs::Packed(output) => {
+                GraphOutputs::Packed(GraphTensor::required(&graph, output)?)
+            }
+            ResolvedOutputs::Separate { policy, value } => GraphOutputs::Separate {
+                policy: GraphTensor::required(&graph, policy)?,
+                value: GraphTensor::required(&graph, value)?,
+            },
+        };
+        let training = match resolved.training {
+            Some(name) => graph
+                .operation_by_name(&name.op)
+                .with_context(|_| format!("Error while extracting the '{}' op.", name.op))?
+                .map(|op| GraphTensor { name, op }),
+            None => None,
+        };
+        let mut training_tensor: tf::Tensor<bool> = tf::Tensor::new(&[]);
+        training_tensor[0] = false;
+        Ok(TensorFlowInferenceEngine {
+            session,
+            graph,
+            example,
+            outputs,
+            training,
+            max_batch_size,
+            timeout,
+            training_tensor,
+            model_dir: p.as_ref().to_owned(),
+            action_count,
+        })
+    }
+
+    // The largest number of inferences that should be run in one session, see batch::InferenceBatcher
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    // How long a partial batch should wait for more inferences before running anyway
+    pub fn timeout(&self) -> std::time::Duration {
+        self.timeout
+    }
+
+    // Check that op returned expected floats for the batch
+    fn check_output_length(
+        &self,
+        op: &GraphTensor,
+        expected: usize,
+        actual: usize,
+    ) -> crate::error::Result<()> {
+        if actual != expected {
+            return Err(InferenceError::WrongOutputLength {
+                op: op.name.to_string(),
+                dir: self.model_dir.clone(),
+                expected,
+                actual,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl GraphTensor {
+    fn required(graph: &tf::Graph, name: TensorName) -> crate::error::Result<Self> {
+        let op = graph
+            .operation_by_name_required(&name.op)
+            .map_err(TensorflowError::from)
+            .with_context(|_| format!("Expected a graph op named '{}'.", name.op))?;
+        Ok(GraphTensor { name, op })
+    }
+}
+
+impl Inference for TensorFlowInferenceEngine {
+    fn infer(&mut self, input: &[u8]) -> crate::error::Result<Priors> {
+        let mut priors = self.infer_batch(&[input])?;
+        Ok(priors.remove(0))
+    }
+
+    // Run one session over a [n, len] tensor of every input at once. All inputs must be the same length.
+    fn infer_batch(&mut self, inputs: &[&[u8]]) -> crate::error::Result<Vec<Priors>> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let len = inputs[0].len();
+        let mut values = Vec::with_capacity(inputs.len() * len);
+        for input in inputs {
+            if input.len() != len {
+                return Err(InferenceError::RaggedBatch {
+                    expected: len,
+                    actual: input.len(),
+                }
+                .into());
+            }
+            values.extend_from_slice(input);
+        }
+        let tensor = tf::Tensor::new(&[inputs.len() as u64, len as u64])
+            .with_values(&values)
+            .map_err(TensorflowError::from)?;
+        let mut inference_step = tf::SessionRunArgs::new();
+
+        inference_step.add_feed(&self.example.op, self.example.name.index, &tensor);
+        if let Some(ref training) = self.training {
+            inference_step.add_feed(&training.op, training.name.index, &self.training_tensor);
+        }
+
+        // Built before the match, which borrows self.outputs while self.session runs
+        let op = self.outputs.names();
+        let dir = self.model_dir.clone();
+        let session_failed = move |tf: tf::Status| InferenceError::SessionFailed {
+            op,
+            shape: vec![inputs.len() as u64, len as u64],
+            dir,
+            tf: tf.into(),
+        };
+        match self.outputs {
+            GraphOutputs::Packed(ref output) => {
+                let token = inference_step.request_fetch(&output.op, output.name.index);
+                let output_tensor: tf::Tensor<f32> = self
+                    .session
+                    .run(&mut inference_step)
+                    .and_then(|_| inference_step.fetch(token))
+                    .map_err(session_failed)?;
+
+                // Each row of the output is [q, ps...] for the corresponding input
+                let row_len = self.action_count + 1;
+                self.check_output_length(output, inputs.len() * row_len, output_tensor.len())?;
+                Ok(output_tensor
+                    .chunks(row_len)
+                    .map(|row| Priors {
+                        ps: row[1..].to_vec(),
+                        q: row[0],
+                    })
+                    .collect())
+            }
+            GraphOutputs::Separate {
+                ref policy,
+                ref value,
+            } => {
+                let policy_token = inference_step.request_fetch(&policy.op, policy.name.index);
+                let value_token = inference_step.request_fetch(&value.op, value.name.index);
+                let (ps, qs): (tf::Tensor<f32>, tf::Tensor<f32>) = self
+                    .session
+                    .run(&mut inference_step)
+                    .and_then(|_| {
+                        Ok((
+                            inference_step.fetch(policy_token)?,
+                            inference_step.fetch(value_token)?,
+                        ))
+                    })
+                    .map_err(session_failed)?;
+
+                self.check_output_length(policy, inputs.len() * self.action_count, ps.len())?;
+                self.check_output_length(value, inputs.len(), qs.len())?;
+                Ok(ps
+                    .chunks(self.action_count)
+                    .zip(qs.iter())
+                    .map(|(ps, &q)| Priors { ps: ps.to_vec(), q })
+                    .collect())
+            }
+        }
+    }
+}
@@ -29,7 +29,7 @@ where
 impl<Inference, State, Game, Player> InteractiveSession<Inference, State, Game, Player>
 where
     Player: game::Player,
-    Inference: inference::Inference,
+    Inference: inference::Inference + Send,
     State: game::GameState + std::fmt::Display,
     Game: game::Game<State = State> + crate::game::AsciiInteractive<Player = Player>,
 {
@@ -88,13 +88,30 @@ where
                     for (i, info) in debug.candidates.iter().enumerate() {
                         println!("{}", self.show_action_info(i, info));
                     }
-                    let hot = if debug.hot { "HOT" } else { "COLD " };
-                    println!("Temperature is {}", hot);
+                    for (i, pv) in debug.principal_variations.iter().enumerate() {
+                        println!("PV {}: {}", i + 1, pv);
+                    }
+                    println!("Temperature is {}", debug.temperature);
+                    println!("c = {:.4}", debug.cpuct);
                     println!("Computer would play {}.", debug.results.selection);
-                    let sec = (debug.time.as_secs() as f64)
-                        + (debug.time.subsec_nanos() as f64 / 1000_000_000.0);
-                    let per_sec = self.options.readouts as f64 / sec;
-                    println!("{:.3}s ({:.2} readouts / s)", sec, per_sec);
+                    if debug.results.resign {
+                        println!("Computer would resign.");
+                    }
+                    let sec = (debug.time.elapsed.as_secs() as f64)
+                        + (debug.time.elapsed.subsec_nanos() as f64 / 1000_000_000.0);
+                    let per_sec = debug.time.readouts as f64 / sec;
+                    println!(
+                        "{:.3}s ({:.2} readouts / s). Stopped by the {:?} limit.",
+                        sec, per_sec, debug.time.limit
+                    );
+                    println!(
+                        "Search tree: {} nodes, {:.1} MiB",
+                        self.searcher.node_count(),
+                        self.searcher.memory_bytes() as f64 / (1024.0 * 1024.0)
+                    );
+                    if self.options.use_transpositions {
+                        println!("{} transpositions", debug.transposition_hits);
+                    }
 
                     let next_action = Self::prompt_next_action_debug_info(&debug);
 
@@ -112,6 +129,10 @@ where
                     println!("Draw.");
                     break;
                 }
+                GameStatus::PlayerToMoveResigned => {
+                    println!("{:?} resigned", self.to_play());
+                    break;
+                }
                 rest @ _ => {
                     println!("{:?}", rest);
                     break;
@@ -135,7 +156,14 @@ where
                                 println!("{}", i);
                             }
 
-                            let next_action = self.get_next_action_interactive(&running);
+                            let next_action = if self.options.ponder {
+                                let limits = self.options.ponder_limits();
+                                self.searcher.ponder(&limits, || {
+                                    Self::get_next_action_interactive(&running)
+                                })?
+                            } else {
+                                Self::get_next_action_interactive(&running)
+                            };
                             self.searcher.apply(next_action);
                         }
                         crate::game::Humanity::Computer => {
@@ -163,6 +191,10 @@ where
                     println!("Draw.");
                     break;
                 }
+                GameStatus::PlayerToMoveResigned => {
+                    println!("{:?} resigned", self.to_play());
+                    break;
+                }
                 rest @ _ => {
                     println!("{:?}", rest);
                     break;
@@ -186,7 +218,7 @@ where
         info.exploration_stimulus,
         info.total_visits)
     }
-    pub fn get_next_action_interactive(&mut self, running: &Arc<AtomicBool>) -> usize {
+    pub fn get_next_action_interactive(running: &Arc<AtomicBool>) -> usize {
         while running.load(Ordering::SeqCst) {
             let mut input = String::new();
             io::stdin().read_line(&mut input);
@@ -51,6 +51,9 @@ where
         self.writer.write(&bytes_crc32_bytes)?;
         Ok(16 + bytes.len())
     }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
 }
 
 pub struct RecordReader<R: Read> {
@@ -2,9 +2,11 @@
 
 pub mod error;
 pub mod game;
+pub mod generate;
 
 pub mod inference;
 pub mod interactive;
+pub mod io;
 pub mod search;
 
 pub mod tictactoe;
@@ -0,0 +1,253 @@
+//! An append-only arena that many threads can push into and read from at once, without locking.
+//! Values never move once they've been pushed, so indices and references into the arena stay
+//! valid for as long as the arena lives.
+//!
+//! Values are stored in chunks that double in size. A chunk is allocated by whichever thread first
+//! needs it, and published with a compare-and-swap, so pushing never blocks and never copies.
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+// Chunk k holds FIRST_CHUNK_LEN << k values
+const FIRST_CHUNK_LEN: usize = 256;
+const CHUNKS: usize = 40;
+
+type Slot<T> = UnsafeCell<MaybeUninit<T>>;
+
+pub struct Arena<T> {
+    len: AtomicUsize,
+    chunks: Vec<AtomicPtr<Slot<T>>>,
+}
+
+// Values are moved in from whichever thread pushed them, and shared by reference with every
+// other thread.
+unsafe impl<T: Send> Send for Arena<T> {}
+unsafe impl<T: Send + Sync> Sync for Arena<T> {}
+
+// The chunk that holds idx, and idx's offset in that chunk
+fn locate(idx: usize) -> (usize, usize) {
+    let bucket = idx / FIRST_CHUNK_LEN + 1;
+    let chunk = (usize::BITS - 1 - bucket.leading_zeros()) as usize;
+    (chunk, idx - chunk_start(chunk))
+}
+
+fn chunk_start(chunk: usize) -> usize {
+    FIRST_CHUNK_LEN * ((1 << chunk) - 1)
+}
+
+fn chunk_len(chunk: usize) -> usize {
+    FIRST_CHUNK_LEN << chunk
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Arena {
+            len: AtomicUsize::new(0),
+            chunks: (0..CHUNKS)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+        }
+    }
+
+    // How many values have been pushed
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    // Move a value into the arena and return its index.
+    // The index must be published to other threads with Release/Acquire ordering (or stronger)
+    // before they read the value through it.
+    pub fn push(&self, value: T) -> usize {
+        // Check for room before taking the slot, so that a full arena never counts a slot that
+        // wasn't written
+        let idx = self
+            .len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                if locate(len).0 < CHUNKS {
+                    Some(len + 1)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|_| panic!("Arena is full"));
+        let (chunk, offset) = locate(idx);
+        let slots = self.chunk(chunk);
+        // Ok, because fetch_add gave this thread exclusive ownership of the slot
+        unsafe {
+            (*slots.add(offset)).get().write(MaybeUninit::new(value));
+        }
+        idx
+    }
+
+    pub fn get(&self, idx: usize) -> &T {
+        let (chunk, offset) = locate(idx);
+        let slots = self.chunks[chunk].load(Ordering::Acquire);
+        assert!(
+            !slots.is_null() && idx < self.len(),
+            "Index {} is out of bounds",
+            idx
+        );
+        // Ok, because indices are only handed out by push, after the value was written
+        unsafe { &*((*slots.add(offset)).get() as *const T) }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> &mut T {
+        let (chunk, offset) = locate(idx);
+        let slots = *self.chunks[chunk].get_mut();
+        assert!(
+            !slots.is_null() && idx < *self.len.get_mut(),
+            "Index {} is out of bounds",
+            idx
+        );
+        unsafe { &mut *((*slots.add(offset)).get() as *mut T) }
+    }
+
+    // The bytes allocated for values, including the unused tail of the last chunk. Doesn't count
+    // memory that the values themselves own.
+    pub fn capacity_bytes(&self) -> usize {
+        (0..CHUNKS)
+            .filter(|&chunk| !self.chunks[chunk].load(Ordering::Acquire).is_null())
+            .map(|chunk| chunk_len(chunk) * std::mem::size_of::<Slot<T>>())
+            .sum()
+    }
+
+    // Move every value out of the arena, in index order
+    pub fn into_vec(mut self) -> Vec<T> {
+        let len = *self.len.get_mut();
+        let mut values = Vec::with_capacity(len);
+        for idx in 0..len {
+            let (chunk, offset) = locate(idx);
+            let slots = *self.chunks[chunk].get_mut();
+            // Ok, because the slot was initialized by push, and len is zeroed below so that
+            // drop won't drop it again
+            unsafe {
+                values.push(ptr::read((*slots.add(offset)).get() as *const T));
+            }
+        }
+        *self.len.get_mut() = 0;
+        values
+    }
+
+    // The chunk at this position, allocating it if no thread has yet
+    fn chunk(&self, chunk: usize) -> *mut Slot<T> {
+        let existing = self.chunks[chunk].load(Ordering::Acquire);
+        if !existing.is_null() {
+            return existing;
+        }
+        let len = chunk_len(chunk);
+        let mut slots: Vec<Slot<T>> = Vec::with_capacity(len);
+        slots.resize_with(len, || UnsafeCell::new(MaybeUninit::uninit()));
+        let fresh = Box::into_raw(slots.into_boxed_slice()) as *mut Slot<T>;
+        match self.chunks[chunk].compare_exchange(
+            ptr::null_mut(),
+            fresh,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => fresh,
+            Err(existing) => {
+                // Another thread allocated this chunk first
+                unsafe {
+                    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(fresh, len)));
+                }
+                existing
+            }
+        }
+    }
+}
+
+impl<T> std::default::Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Arena<T> {
+    fn drop(&mut self) {
+        let len = *self.len.get_mut();
+        for chunk in 0..CHUNKS {
+            let slots = *self.chunks[chunk].get_mut();
+            if slots.is_null() {
+                continue;
+            }
+            let initialized =
+                std::cmp::min(chunk_len(chunk), len.saturating_sub(chunk_start(chunk)));
+            unsafe {
+                for i in 0..initialized {
+                    ptr::drop_in_place((*slots.add(i)).get() as *mut T);
+                }
+                drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                    slots,
+                    chunk_len(chunk),
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn locate_spans_chunks() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(FIRST_CHUNK_LEN - 1), (0, FIRST_CHUNK_LEN - 1));
+        assert_eq!(locate(FIRST_CHUNK_LEN), (1, 0));
+        assert_eq!(locate(3 * FIRST_CHUNK_LEN), (2, 0));
+        assert_eq!(
+            locate(3 * FIRST_CHUNK_LEN - 1),
+            (1, 2 * FIRST_CHUNK_LEN - 1)
+        );
+    }
+
+    #[test]
+    fn concurrent_pushes_keep_every_value() {
+        let arena = Arena::new();
+        let threads = 8;
+        let per_thread = 3 * FIRST_CHUNK_LEN;
+        let pushed: Vec<(usize, usize)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|thread| {
+                    let arena = &arena;
+                    scope.spawn(move || {
+                        (0..per_thread)
+                            .map(|i| {
+                                let value = thread * per_thread + i;
+                                (arena.push(value), value)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        assert_eq!(arena.len(), threads * per_thread);
+        let mut seen = vec![false; threads * per_thread];
+        for (idx, value) in pushed {
+            assert_eq!(*arena.get(idx), value);
+            assert!(!seen[value]);
+            seen[value] = true;
+        }
+        assert_eq!(arena.into_vec().len(), threads * per_thread);
+    }
+
+    #[test]
+    fn drop_drops_every_value_once() {
+        let counter = Arc::new(());
+        let arena = Arena::new();
+        for _ in 0..FIRST_CHUNK_LEN + 1 {
+            arena.push(counter.clone());
+        }
+        assert_eq!(Arc::strong_count(&counter), FIRST_CHUNK_LEN + 2);
+        drop(arena);
+        assert_eq!(Arc::strong_count(&counter), 1);
+    }
+}
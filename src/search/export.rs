@@ -0,0 +1,345 @@
+//! Exports the search tree below the root for visualization: DOT for Graphviz, or JSON for a notebook.
+//! The export is a tree even when the search tree is a DAG. A node that is reached by several move
+//! orders appears once below each of them. A node that leads back to one of the nodes above it, i.e. a
+//! cycle of transpositions, is exported without its children the second time.
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+use super::{exploration_stimulus, first_play_value, SearchTreeOptions};
+use crate::error::Result;
+use crate::search::tree::{NodeIdx, Tree};
+
+// Which part of the tree to export
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportOptions {
+    pub max_depth: usize,  // How many plies below the root to export
+    pub min_visits: usize, // Leave out actions that have been visited fewer times than this
+}
+impl std::default::Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            min_visits: 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedNode {
+    pub state: String,          // The state's Debug representation
+    pub action: Option<usize>,  // The action that leads here from the parent. None at the root.
+    pub prior: Option<f32>,     // Prior after scaling and noise
+    pub raw_prior: Option<f32>, // The expert's prior
+    pub visits: usize,          // Visits through the action, or to the node itself at the root
+    pub wins: usize,            // Readouts through the action that ended in a terminal win
+    pub losses: usize,
+    pub average_value: Option<f64>, // Q(s, a), for the player who took the action. None until visited.
+    pub exploration_stimulus: Option<f64>, // Q(s, a) + U(s, a), as of the export
+    pub children: Vec<ExportedNode>,
+}
+
+impl ExportedNode {
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph search_tree {{")?;
+        writeln!(writer, "    node [shape=box, fontname=monospace];")?;
+        let mut next_id = 0;
+        self.write_dot_node(&mut writer, &mut next_id)?;
+        writeln!(writer, "}}")
+    }
+
+    // Write this node, its children, and the edges to them. Returns the node's id.
+    fn write_dot_node<W: Write>(&self, writer: &mut W, next_id: &mut usize) -> io::Result<usize> {
+        let id = *next_id;
+        *next_id += 1;
+        let mut label = match self.action {
+            Some(action) => format!("action {}\n", action),
+            None => "root\n".to_string(),
+        };
+        if let (Some(prior), Some(raw_prior)) = (self.prior, self.raw_prior) {
+            label += &format!("P = {:.3} (raw {:.3})\n", prior, raw_prior);
+        }
+        label += &format!("N = {}, W/L = {}/{}", self.visits, self.wins, self.losses);
+        if let Some(q) = self.average_value {
+            label += &format!("\nQ = {:.3}", q);
+        }
+        if let Some(stimulus) = self.exploration_stimulus {
+            label += &format!("\nQ + U = {:.3}", stimulus);
+        }
+        writeln!(
+            writer,
+            "    n{} [label=\"{}\", tooltip=\"{}\"];",
+            id,
+            escape(&label),
+            escape(&self.state)
+        )?;
+        for child in &self.children {
+            let child_id = child.write_dot_node(writer, next_id)?;
+            writeln!(
+                writer,
+                "    n{} -> n{} [label=\"{}\"];",
+                id,
+                child_id,
+                child.action.unwrap_or_default()
+            )?;
+        }
+        Ok(id)
+    }
+}
+
+// Export the part of tree below root that options selects
+pub(super) fn export<State>(
+    tree: &Tree<State>,
+    root_idx: NodeIdx,
+    search_options: &SearchTreeOptions,
+    options: &ExportOptions,
+) -> ExportedNode
+where
+    State: std::fmt::Debug,
+{
+    let root = &tree[root_idx];
+    ExportedNode {
+        state: format!("{:?}", root.state()),
+        action: None,
+        prior: None,
+        raw_prior: None,
+        visits: root.visits(),
+        wins: 0,
+        losses: 0,
+        average_value: None,
+        exploration_stimulus: None,
+        children: export_children(tree, &mut vec![root_idx], search_options, options),
+    }
+}
+
+// Export the children of the last node on path. Path holds every node from the root down, so that a
+// cycle can't be followed around again.
+fn export_children<State>(
+    tree: &Tree<State>,
+    path: &mut Vec<NodeIdx>,
+    search_options: &SearchTreeOptions,
+    options: &ExportOptions,
+) -> Vec<ExportedNode>
+where
+    State: std::fmt::Debug,
+{
+    if path.len() > options.max_depth {
+        return vec![];
+    }
+    let node = &tree[*path.last().unwrap()];
+    let parent_visits = node.visits();
+    let first_play_value = first_play_value(node, search_options);
+    node.edges()
+        .iter()
+        .filter(|edge| edge.visit_count() >= options.min_visits)
+        .map(|edge| {
+            let child = &tree[edge.child()];
+            let children = if path.contains(&edge.child()) {
+                vec![]
+            } else {
+                path.push(edge.child());
+                let children = export_children(tree, path, search_options, options);
+                path.pop();
+                children
+            };
+            ExportedNode {
+                state: format!("{:?}", child.state()),
+                action: Some(edge.action()),
+                prior: Some(edge.prior()),
+                raw_prior: Some(edge.raw_prior()),
+                visits: edge.visit_count(),
+                wins: edge.wins(),
+                losses: edge.losses(),
+                average_value: if edge.visit_count() > 0 {
+                    Some(edge.total_value() / edge.visit_count() as f64)
+                } else {
+                    None
+                },
+                exploration_stimulus: Some(exploration_stimulus(
+                    search_options,
+                    edge,
+                    parent_visits,
+                    first_play_value,
+                )),
+                children,
+            }
+        })
+        .collect()
+}
+
+// Quote a string for a DOT label
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::tree::Successor;
+    use crate::search::SearchTree;
+    use crate::tictactoe::TicTacToe;
+
+    // Uniform priors and an even position
+    #[derive(Debug)]
+    struct Uniform;
+
+    impl crate::inference::Inference for Uniform {
+        fn infer(&mut self, _input: &[u8]) -> Result<crate::inference::Priors> {
+            Ok(crate::inference::Priors {
+                ps: vec![1.0 / 9.0; 9],
+                q: 0.0,
+            })
+        }
+    }
+
+    // The nodes in the order write_dot numbers them
+    fn preorder<'a>(node: &'a ExportedNode, nodes: &mut Vec<&'a ExportedNode>) {
+        nodes.push(node);
+        for child in &node.children {
+            preorder(child, nodes);
+        }
+    }
+
+    // The JSON parser can be off in the last bit of a float
+    fn assert_close(a: Option<f64>, b: Option<f64>) {
+        match (a, b) {
+            (Some(a), Some(b)) => assert!((a - b).abs() < 1e-6, "{} != {}", a, b),
+            _ => assert_eq!(a, b),
+        }
+    }
+
+    fn assert_same(read: &ExportedNode, exported: &ExportedNode) {
+        assert_eq!(read.state, exported.state);
+        assert_eq!(read.action, exported.action);
+        assert_eq!(
+            (read.visits, read.wins, read.losses),
+            (exported.visits, exported.wins, exported.losses)
+        );
+        assert_close(read.prior.map(f64::from), exported.prior.map(f64::from));
+        assert_close(
+            read.raw_prior.map(f64::from),
+            exported.raw_prior.map(f64::from),
+        );
+        assert_close(read.average_value, exported.average_value);
+        assert_close(read.exploration_stimulus, exported.exploration_stimulus);
+        assert_eq!(read.children.len(), exported.children.len());
+        for (read, exported) in read.children.iter().zip(&exported.children) {
+            assert_same(read, exported);
+        }
+    }
+
+    fn assert_round_trips(exported: &ExportedNode) {
+        let mut json = vec![];
+        exported.write_json(&mut json).unwrap();
+        let read: ExportedNode = serde_json::from_slice(&json).unwrap();
+        assert_same(&read, exported);
+
+        let mut dot = vec![];
+        exported.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        let mut nodes = vec![];
+        preorder(exported, &mut nodes);
+        let labels: Vec<&str> = dot
+            .lines()
+            .filter(|line| line.contains("tooltip"))
+            .collect();
+        assert_eq!(labels.len(), nodes.len());
+        for (id, (line, node)) in labels.iter().zip(&nodes).enumerate() {
+            assert!(line.starts_with(&format!("    n{} [", id)), "{}", line);
+            assert!(line.contains(&format!("N = {},", node.visits)), "{}", line);
+        }
+        let arrows: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(arrows.len(), nodes.len() - 1);
+        for line in arrows {
+            let child: usize = line
+                .split(" -> n")
+                .nth(1)
+                .unwrap()
+                .split(' ')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap();
+            let action = nodes[child].action.unwrap();
+            assert!(
+                line.ends_with(&format!("[label=\"{}\"];", action)),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn a_searched_tree_round_trips() {
+        for &use_transpositions in &[false, true] {
+            let options = SearchTreeOptions {
+                readouts: 200,
+                use_value_head: true,
+                use_transpositions,
+                seed: Some(11),
+                ..Default::default()
+            };
+            let mut search = SearchTree::init_with_options(Uniform, TicTacToe::new(), options);
+            search.read().unwrap();
+            // Exported once below each move order that reaches it
+            assert_eq!(
+                search.search_tree.transposition_hits() > 0,
+                use_transpositions
+            );
+            let exported = search.export(&ExportOptions {
+                max_depth: 3,
+                min_visits: 2,
+            });
+            assert_eq!(exported.action, None);
+            assert!(!exported.children.is_empty());
+            for child in &exported.children {
+                assert!(child.visits >= 2);
+                assert!(child
+                    .children
+                    .iter()
+                    .all(|grandchild| grandchild.visits >= 2));
+            }
+            assert_round_trips(&exported);
+        }
+    }
+
+    #[test]
+    fn a_cycle_of_transpositions_is_exported_once() {
+        // 0 -> 1 -> 2 -> 0, through the transposition table
+        let tree = Tree::with_transpositions();
+        let root = tree.add_root(0u32);
+        let mut idx = root;
+        for state in 1..4 {
+            let successor = Successor {
+                action: state as usize,
+                prior: 1.0,
+                raw_prior: 1.0,
+                state: state % 3,
+            };
+            tree.begin_expansion(idx)
+                .unwrap()
+                .finish(0.0, std::iter::once(successor));
+            idx = tree[idx].edges()[0].child();
+        }
+        assert_eq!(idx, root);
+        assert_eq!(tree.node_count(), 3);
+
+        let options = ExportOptions {
+            max_depth: 100,
+            min_visits: 0,
+        };
+        let exported = export(&tree, root, &SearchTreeOptions::default(), &options);
+        let mut nodes = vec![];
+        preorder(&exported, &mut nodes);
+        let states: Vec<&str> = nodes.iter().map(|node| node.state.as_str()).collect();
+        assert_eq!(states, vec!["0", "1", "2", "0"]);
+        assert!(nodes[3].children.is_empty());
+        assert_round_trips(&exported);
+    }
+}
@@ -0,0 +1,433 @@
+//! Gumbel AlphaZero root search, from "Policy improvement by planning with Gumbel" (Danihelka et al., 2022).
+//! Instead of letting PUCT spread the readouts over the root's actions, the root samples m actions
+//! without replacement with the Gumbel-top-k trick, and divides the readouts between them by sequential
+//! halving: each phase gives every remaining action an equal share of the budget, then drops the worse
+//! half of them. The action that survives is an improvement on the expert's policy even when there are
+//! only a handful of readouts, and the completed Q values of the root's actions give an improved policy
+//! to train the expert on.
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{read_out, SearchRng, SearchTreeOptions};
+use crate::error::{Result, SearchError};
+use crate::search::limit::{LimitReached, SearchLimits};
+use crate::search::tree::{Edge, Expansion, Node, NodeIdx, Tree};
+use crate::{game, inference};
+use std::{sync::atomic::AtomicBool, time};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GumbelOptions {
+    pub considered_actions: usize, // m, how many root actions are sampled for sequential halving
+    // Q values are scaled by sigma(q) = (c_visit + max_b N(b)) * c_scale * q before they're added to
+    // the logits, so that the search trusts them more as the actions get more visits
+    pub c_visit: f32,
+    pub c_scale: f32,
+}
+impl std::default::Default for GumbelOptions {
+    // These defaults are the values used in the paper.
+    fn default() -> Self {
+        Self {
+            considered_actions: 16,
+            c_visit: 50.0,
+            c_scale: 1.0,
+        }
+    }
+}
+
+// Divide the readouts that the limits allow among the root's actions by sequential halving. Returns how
+// many readouts were done, which limit stopped them, and the action that sequential halving picked, if
+// the root has any legal actions. That's the action to play: with noise, it's a sample from the
+// improved policy. Without noise, as once the temperature is 0, it's the best of the likeliest actions.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn search<Inference, State, Game>(
+    inference: &mut Inference,
+    tree: &Tree<State>,
+    root_idx: NodeIdx,
+    options: &SearchTreeOptions,
+    gumbel: &GumbelOptions,
+    noise: bool,
+    game: &Game,
+    limits: &SearchLimits,
+    rng: &mut SearchRng,
+) -> Result<(usize, LimitReached, Option<usize>)>
+where
+    Inference: inference::Inference + Send,
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    limits.validate()?;
+    // Sequential halving plans the whole read up front, so it needs to know how many readouts it has
+    let root_visits = tree[root_idx].visits();
+    let (budget, budget_limit) = limits
+        .readouts
+        .map(|readouts| (readouts, LimitReached::Readouts))
+        .into_iter()
+        .chain(limits.total_visits.map(|total_visits| {
+            (
+                total_visits.saturating_sub(root_visits),
+                LimitReached::TotalVisits,
+            )
+        }))
+        .min_by_key(|(readouts, _)| *readouts)
+        .ok_or_else(|| SearchError::InvalidConfiguration {
+            msg: "Gumbel root search needs a readout or total visit limit".to_string(),
+        })?;
+    let deadline = limits.time.map(|time| time::Instant::now() + time);
+    let stop = AtomicBool::new(false);
+
+    // Read out at most readouts more times, through root_action if it's given. Returns how many
+    // readouts were done, and the limit that stopped the whole search early, if any.
+    // The rng is an argument rather than captured, since the Gumbel noise needs it between reads.
+    let mut read = |readouts: usize,
+                    root_action: Option<usize>,
+                    rng: &mut SearchRng|
+     -> Result<(usize, Option<LimitReached>)> {
+        let now = time::Instant::now();
+        let time = match deadline {
+            Some(deadline) if now >= deadline => return Ok((0, Some(LimitReached::Time))),
+            Some(deadline) => Some(deadline - now),
+            None => None,
+        };
+        let limits = SearchLimits {
+            readouts: Some(readouts),
+            time,
+            ..Default::default()
+        };
+        let (readouts, limit) = read_out(
+            inference,
+            tree,
+            root_idx,
+            options,
+            game,
+            &limits,
+            &stop,
+            root_action,
+            rng,
+        )?;
+        Ok(match limit {
+            LimitReached::Readouts => (readouts, None),
+            limit => (readouts, Some(limit)),
+        })
+    };
+
+    let mut used = 0;
+    // The root's priors are needed to sample its actions
+    if tree[root_idx].expansion() != Expansion::Expanded && budget > 0 {
+        let (readouts, limit) = read(1, None, rng)?;
+        used += readouts;
+        if let Some(limit) = limit {
+            return Ok((used, limit, None));
+        }
+    }
+    let root = &tree[root_idx];
+    let legal_actions = game.legal_actions(root.state());
+    let edges: Vec<&Edge> = root
+        .edges()
+        .iter()
+        .filter(|edge| legal_actions[edge.action()])
+        .collect();
+    if edges.is_empty() {
+        return Ok((used, budget_limit, None));
+    }
+
+    // Perturbing the logits with Gumbel noise and taking the top m is sampling m actions from the
+    // prior without replacement
+    let mut candidates: Vec<(&Edge, f64)> = edges
+        .iter()
+        .map(|&edge| {
+            if !noise {
+                return (edge, logit(edge));
+            }
+            let u: f64 = rng.gen_range(f64::EPSILON, 1.0);
+            (edge, logit(edge) - (-u.ln()).ln())
+        })
+        .collect();
+    candidates.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let considered = std::cmp::max(1, std::cmp::min(gumbel.considered_actions, edges.len()));
+    candidates.truncate(considered);
+
+    let phases = std::cmp::max(1, (considered as f64).log2().ceil() as usize);
+    'phases: for phase in 0..phases {
+        // Every remaining action gets an equal share of what's left for this phase, and at least one readout
+        let share = (budget - used) / ((phases - phase) * candidates.len());
+        let visits = std::cmp::max(1, share);
+        for &(edge, _) in &candidates {
+            let readouts = std::cmp::min(visits, budget - used);
+            if readouts == 0 {
+                break 'phases;
+            }
+            let (readouts, limit) = read(readouts, Some(edge.action()), rng)?;
+            used += readouts;
+            if let Some(limit) = limit {
+                sort_by_score(&mut candidates, root, gumbel);
+                return Ok((used, limit, Some(candidates[0].0.action())));
+            }
+        }
+        sort_by_score(&mut candidates, root, gumbel);
+        if phase + 1 < phases {
+            let survivors = candidates.len().div_ceil(2);
+            candidates.truncate(survivors);
+        }
+    }
+    Ok((used, budget_limit, Some(candidates[0].0.action())))
+}
+
+// The improved policy, softmax(logits + sigma(completed Q)), over the root's legal actions. This is the
+// training target of a Gumbel search.
+pub(super) fn improved_policy<State>(
+    node: &Node<State>,
+    legal_actions: &[bool],
+    gumbel: &GumbelOptions,
+) -> Vec<f32> {
+    let mut policy = vec![0.0; legal_actions.len()];
+    let edges: Vec<&Edge> = node
+        .edges()
+        .iter()
+        .filter(|edge| legal_actions[edge.action()])
+        .collect();
+    let scores: Vec<f64> = edges
+        .iter()
+        .map(|edge| logit(edge) + sigma(node, completed_q(node, edge), gumbel))
+        .collect();
+    let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = scores.iter().map(|score| (score - max).exp()).collect();
+    let sum: f64 = exps.iter().sum();
+    for (edge, exp) in edges.iter().zip(exps) {
+        policy[edge.action()] = (exp / sum) as f32;
+    }
+    policy
+}
+
+// Best first, by gumbel + logit + sigma(completed Q)
+fn sort_by_score<State>(
+    candidates: &mut [(&Edge, f64)],
+    root: &Node<State>,
+    gumbel: &GumbelOptions,
+) {
+    let score = |&(edge, perturbed_logit): &(&Edge, f64)| {
+        perturbed_logit + sigma(root, completed_q(root, edge), gumbel)
+    };
+    candidates.sort_by(|a, b| {
+        score(b)
+            .partial_cmp(&score(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+fn logit(edge: &Edge) -> f64 {
+    (edge.raw_prior() as f64).max(1e-12).ln()
+}
+
+// Monotonically transform q, on [-1, 1], onto the scale of the logits
+fn sigma<State>(node: &Node<State>, q: f64, gumbel: &GumbelOptions) -> f64 {
+    let max_visits = node
+        .edges()
+        .iter()
+        .map(|edge| edge.visit_count())
+        .max()
+        .unwrap_or(0);
+    let normalized = (q + 1.0) / 2.0;
+    (gumbel.c_visit as f64 + max_visits as f64) * gumbel.c_scale as f64 * normalized
+}
+
+// The average value of the edge if it has been visited. Otherwise an estimate of the node's value that
+// mixes the expert's q with the prior-weighted average of the visited actions' values.
+fn completed_q<State>(node: &Node<State>, edge: &Edge) -> f64 {
+    if edge.visit_count() > 0 {
+        return edge.total_value() / edge.visit_count() as f64;
+    }
+    let value = node.value().unwrap_or(0.0) as f64;
+    let visits: usize = node.edges().iter().map(|edge| edge.visit_count()).sum();
+    if visits == 0 {
+        return value;
+    }
+    let (weight, weighted_q) = node
+        .edges()
+        .iter()
+        .filter(|edge| edge.visit_count() > 0)
+        .fold((0.0, 0.0), |(weight, weighted_q), edge| {
+            let prior = edge.raw_prior() as f64;
+            let q = edge.total_value() / edge.visit_count() as f64;
+            (weight + prior, weighted_q + prior * q)
+        });
+    if weight == 0.0 {
+        return value;
+    }
+    (value + visits as f64 * weighted_q / weight) / (1.0 + visits as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::search::search_rng;
+    use crate::search::tree::Successor;
+    use crate::tictactoe::{State, TicTacToe};
+
+    // A root with the given q and one edge per prior, to states 1, 2, ...
+    fn root(q: f32, priors: &[f32]) -> (Tree<u32>, NodeIdx) {
+        let tree = Tree::new();
+        let root = tree.add_root(0);
+        let successors = priors.iter().enumerate().map(|(action, &prior)| Successor {
+            action,
+            prior,
+            raw_prior: prior,
+            state: action as u32 + 1,
+        });
+        tree.begin_expansion(root).unwrap().finish(q, successors);
+        (tree, root)
+    }
+
+    fn visit(edge: &Edge, value: f64) {
+        edge.add_virtual_loss();
+        edge.backup(value, 0, 0);
+    }
+
+    #[test]
+    fn unvisited_actions_are_completed_with_the_value_of_the_node() {
+        let (tree, root) = root(0.5, &[0.5, 0.25, 0.25]);
+        let node = &tree[root];
+        let edges = node.edges();
+        // With nothing visited, that's the expert's q
+        assert_eq!(completed_q(node, &edges[2]), 0.5);
+
+        visit(&edges[0], 1.0);
+        visit(&edges[0], 0.0);
+        visit(&edges[1], -1.0);
+        assert_eq!(completed_q(node, &edges[0]), 0.5);
+        assert_eq!(completed_q(node, &edges[1]), -1.0);
+        // The visited actions' prior-weighted q is (0.5 * 0.5 + 0.25 * -1) / 0.75 = 0, which 3 visits
+        // weigh against the expert's 0.5
+        assert_eq!(completed_q(node, &edges[2]), 0.125);
+    }
+
+    #[test]
+    fn sigma_grows_with_the_most_visited_action() {
+        let gumbel = GumbelOptions::default();
+        let (tree, root) = root(0.0, &[0.5, 0.5]);
+        let node = &tree[root];
+        assert_eq!(sigma(node, 1.0, &gumbel), 50.0);
+        assert_eq!(sigma(node, -1.0, &gumbel), 0.0);
+
+        visit(&node.edges()[0], 0.0);
+        visit(&node.edges()[0], 0.0);
+        visit(&node.edges()[1], 0.0);
+        assert_eq!(sigma(node, 1.0, &gumbel), 52.0);
+        assert_eq!(sigma(node, 0.0, &gumbel), 26.0);
+    }
+
+    #[test]
+    fn the_improved_policy_moves_the_prior_towards_the_better_actions() {
+        let gumbel = GumbelOptions::default();
+        let (tree, root) = root(0.0, &[0.5, 0.25, 0.25]);
+        let node = &tree[root];
+        let legal = [true, true, false];
+        // Every action is completed with the same q, so the legal actions keep their priors
+        let policy = improved_policy(node, &legal, &gumbel);
+        assert!((policy[0] - 2.0 / 3.0).abs() < 1e-6);
+        assert!((policy[1] - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(policy[2], 0.0);
+
+        visit(&node.edges()[0], -1.0);
+        visit(&node.edges()[1], 1.0);
+        let policy = improved_policy(node, &legal, &gumbel);
+        assert!(policy[1] > 0.99);
+        assert!((policy.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    }
+
+    // Priors that prefer the lower actions, and a q of 0
+    #[derive(Debug)]
+    struct Descending;
+
+    impl inference::Inference for Descending {
+        fn infer(&mut self, _input: &[u8]) -> Result<inference::Priors> {
+            Ok(inference::Priors {
+                ps: (0..9).map(|action| 9.0 - action as f32).collect(),
+                q: 0.0,
+            })
+        }
+    }
+
+    fn options(considered_actions: usize) -> (SearchTreeOptions, GumbelOptions) {
+        let gumbel = GumbelOptions {
+            considered_actions,
+            ..Default::default()
+        };
+        let options = SearchTreeOptions {
+            use_value_head: true,
+            seed: Some(3),
+            gumbel: Some(gumbel),
+            ..Default::default()
+        };
+        (options, gumbel)
+    }
+
+    fn halve(
+        state: State,
+        considered_actions: usize,
+        noise: bool,
+        readouts: usize,
+    ) -> (Tree<State>, NodeIdx, usize, Option<usize>) {
+        let (options, gumbel) = options(considered_actions);
+        let tree = Tree::new();
+        let root = tree.add_root(state);
+        let limits = SearchLimits {
+            readouts: Some(readouts),
+            ..Default::default()
+        };
+        let (used, limit, winner) = search(
+            &mut Descending,
+            &tree,
+            root,
+            &options,
+            &gumbel,
+            noise,
+            &TicTacToe::new(),
+            &limits,
+            &mut search_rng(&options),
+        )
+        .unwrap();
+        assert_eq!(limit, LimitReached::Readouts);
+        (tree, root, used, winner)
+    }
+
+    #[test]
+    fn sequential_halving_gives_the_survivors_the_rest_of_the_readouts() {
+        // One readout expands the root. 4 actions get 4 readouts each in the first phase, and the 2
+        // that survive it 8 more each in the second.
+        let (tree, root, used, winner) = halve(State::default(), 4, true, 33);
+        assert_eq!(used, 33);
+        let visits: Vec<usize> = tree[root]
+            .edges()
+            .iter()
+            .map(|edge| edge.visit_count())
+            .collect();
+        assert_eq!(visits.iter().sum::<usize>(), 33);
+        assert_eq!(visits.iter().filter(|&&n| n >= 12).count(), 2);
+        assert_eq!(visits.iter().filter(|&&n| n > 0 && n < 12).count(), 2);
+        assert!(visits[winner.unwrap()] >= 12);
+    }
+
+    #[test]
+    fn without_noise_the_likeliest_actions_are_considered() {
+        let (tree, root, _, winner) = halve(State::default(), 1, false, 9);
+        assert_eq!(winner, Some(0));
+        assert!(tree[root].edges()[0].visit_count() >= 8);
+    }
+
+    #[test]
+    fn a_single_legal_action_gets_every_readout() {
+        // X: 0 2 3 7, O: 1 4 5 6, and X to play 8 for a draw
+        let game = TicTacToe::new();
+        let state = [0, 1, 2, 4, 3, 5, 7, 6]
+            .iter()
+            .fold(State::default(), |state, &action| {
+                game.successor(&state, action)
+            });
+        let (tree, root, used, winner) = halve(state, 16, true, 8);
+        assert_eq!(winner, Some(8));
+        assert_eq!(used, 8);
+        assert_eq!(tree[root].edges().len(), 1);
+        assert_eq!(tree[root].edges()[0].visit_count(), 8);
+    }
+}
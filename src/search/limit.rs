@@ -0,0 +1,65 @@
+//! How long a read goes on for. A read stops at whichever of its limits is reached first.
+use std::time;
+
+use crate::error::{Result, SearchError};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub readouts: Option<usize>,      // How many readouts this read may do
+    pub time: Option<time::Duration>, // How long this read may take
+    // How many visits the root may have, counting the visits it kept from earlier reads
+    pub total_visits: Option<usize>,
+    // Stop as soon as the remaining readouts couldn't change which action has the most visits.
+    // Needs one of the other limits to know how many readouts remain.
+    pub stop_when_decided: bool,
+}
+
+impl SearchLimits {
+    pub fn readouts(readouts: usize) -> Self {
+        SearchLimits {
+            readouts: Some(readouts),
+            ..Default::default()
+        }
+    }
+
+    pub fn time(time: time::Duration) -> Self {
+        SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+
+    pub fn total_visits(total_visits: usize) -> Self {
+        SearchLimits {
+            total_visits: Some(total_visits),
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.readouts.is_none() && self.time.is_none() && self.total_visits.is_none() {
+            return Err(SearchError::InvalidConfiguration {
+                msg: "A read needs a readout, time, or total visit limit".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+// The limit that ended a read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitReached {
+    Readouts,
+    Time,
+    TotalVisits,
+    Decided,
+    Stopped, // The read was ended from outside, e.g. because pondering is over
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchTime {
+    pub elapsed: time::Duration, // How long the read took
+    pub readouts: usize,         // How many readouts it did
+    pub limit: LimitReached,     // Which limit stopped it
+}
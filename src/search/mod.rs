@@ -0,0 +1,1906 @@
+//! This module implements informed Monte Carlo Tree Search. "Informed" means that the search is guided by an expert policy
+//! that ascribes Bayesian prior probabilities to question of whether each possible next action is the best one.
+//! Consumers of the Seraphim library are to implement the GameExpert trait, and pass an instance of GameExpert
+//! to SearchTree.
+use rand::distributions::{Dirichlet, Distribution};
+use rand::{FromEntropy, Rng, SeedableRng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{game, game::GameStatus, inference};
+use std::{
+    collections::HashMap,
+    default::Default,
+    fs,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time,
+};
+
+mod arena;
+pub mod export;
+pub mod gumbel;
+pub mod limit;
+pub mod resign;
+pub mod temperature;
+pub mod tree;
+
+use self::gumbel::GumbelOptions;
+use self::limit::{LimitReached, SearchLimits};
+use self::temperature::TemperatureSchedule;
+use self::tree::{Edge, Expanding, Expansion, Node, NodeIdx, Successor, Tree, TreeSnapshot};
+
+use crate::error::{InferenceError, Result, SearchError};
+
+#[derive(Debug)]
+pub struct CandidateActionDebugInformation {
+    pub action: usize,
+    pub prior: f32,     // Prior after scaling and noise
+    pub posterior: f32, // The improved probability that this move is the best after PUCT search
+    pub raw: f32,
+    pub total_visits: usize, // how many times has this line of play been sampled, in total
+    pub total_value: f64, // W(s, a) in the paper. The sum of the values backed up through this action.
+    pub wins: usize,      // How many readouts through this action reached a terminal win
+    pub losses: usize,
+    pub visits_in_last_read: usize, // how many times was this line of play sampled in the most recent read
+    pub average_value: f64,         // The average value of taking this action Q(s, a) in the paper
+    pub exploration_stimulus: f64, // How badly does the search tree want to explore this action in the future?
+    // The highest value of here is the node it would sample next if asked to perform more readouts.
+    pub first_play_urgency: FirstPlayUrgency, // How the search valued this action before it was visited
+    pub first_play_value: f64, // The value that strategy gives to the root's unvisited actions, as of the end of the read
+}
+
+#[derive(Debug)]
+pub struct SearchResultsDebugInfo {
+    pub time: limit::SearchTime, // How long it took to compute this move, and which limit stopped the search
+    pub candidates: Vec<CandidateActionDebugInformation>,
+    pub results: SearchResultsInfo,
+    pub temperature: f32, // The temperature this move was sampled at. Above 0 introduces noise early in the game to ensure game variety.
+    pub transposition_hits: usize, // How many expansions in this read reached a state that was already in the tree
+    pub cpuct: f64, // The exploration constant c at the root, as of the end of the read
+    // The lines the search expects after its options.multi_pv most visited candidates, most visited first
+    pub principal_variations: Vec<PrincipalVariation>,
+}
+
+// A line of play that the search expects: the most visited action at every node along it
+#[derive(Debug)]
+pub struct PrincipalVariation {
+    pub moves: Vec<PrincipalVariationMove>,
+}
+
+#[derive(Debug)]
+pub struct PrincipalVariationMove {
+    pub action: usize,
+    pub visits: usize,
+    pub average_value: f64, // Q(s, a), for the player who makes this move
+}
+
+impl std::fmt::Display for PrincipalVariation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, m) in self.moves.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{} (N={}, Q={:.3})", m.action, m.visits, m.average_value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SearchResultsInfo {
+    pub results: Vec<f32>,
+    pub selection: usize,
+    pub application_token: ApplicationToken,
+    pub resign: bool, // The player to move resigns instead of playing selection. Applying these results ends the game.
+    // Whether results should be trained on. False for the moves that options.playout_cap gave a cheap
+    // search: they're played, but their results are too noisy to train the policy with.
+    pub policy_target: bool,
+}
+
+// An opaque token which can be efficient 'applied' to search tree to advance the root of the tree to the next node
+#[derive(Debug)]
+pub struct ApplicationToken(NodeIdx);
+
+#[derive(Debug)]
+struct PValues {
+    // The raw prior we got from the net, before normalizing legal actions to sum to 1 and
+    // introducing noise
+    raw_priors: crate::inference::Priors,
+    // P(s, a). The prior probability of choosing this node, derived from the expert guess.
+    noised_and_scaled_priors: Vec<f32>,
+    legal_actions: Vec<bool>, // Every legal action gets an edge, even if its prior is 0
+}
+
+// What Q(s, a) to explore an action with before it has been visited
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FirstPlayUrgency {
+    Draw,             // Unvisited actions are worth 0
+    Loss, // Unvisited actions are worth -1, so the search tries them only when the prior insists
+    ParentMinus(f32), // Unvisited actions are worth the average value of the visited ones, less this reduction
+}
+
+impl std::str::FromStr for FirstPlayUrgency {
+    type Err = SearchError;
+    // "draw", "loss", or "parent:<reduction>"
+    fn from_str(s: &str) -> std::result::Result<Self, SearchError> {
+        let invalid = || SearchError::InvalidConfiguration {
+            msg: format!(
+                "Unknown first play urgency '{}'. Expected draw, loss, or parent:<reduction>",
+                s
+            ),
+        };
+        match s {
+            "draw" => Ok(FirstPlayUrgency::Draw),
+            "loss" => Ok(FirstPlayUrgency::Loss),
+            _ if s.starts_with("parent:") => s["parent:".len()..]
+                .parse()
+                .map(FirstPlayUrgency::ParentMinus)
+                .map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, StructOpt)]
+#[structopt(
+    name = "seraphim config",
+    about = "Basic configuration that must be defined for any seraphim program."
+)]
+pub struct SeraphimConfig {
+    #[structopt(env = "SERAPHIM_MODEL_NAME", long)]
+    pub model_name: String,
+
+    #[structopt(env = "SERAPHIM_DATA", long)]
+    pub seraphim_data: String,
+}
+
+#[derive(Clone, Debug, PartialEq, StructOpt)]
+#[structopt(
+    name = "search options",
+    about = "Hyperparamters for configuring tree search."
+)]
+pub struct SearchTreeParamOverrides {
+    #[structopt(
+        long,
+        help = "A constant determining the tradeoff between exploration and exploitation. Higher values bias towards exploration."
+    )]
+    pub cpuct: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "Grow c with the number of visits to the parent, as in AlphaZero: c = ln((N + cpuct_base + 1) / cpuct_base) + cpuct_init. Overrides --cpuct."
+    )]
+    pub cpuct_base: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "c_init in the AlphaZero schedule, the value of c at an unvisited node. Overrides --cpuct."
+    )]
+    pub cpuct_init: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "How many of the most visited candidates to show the principal variation of when debugging."
+    )]
+    pub multi_pv: Option<usize>,
+
+    #[structopt(
+        long = "raw",
+        help = "Play the game using raw scores from the expert, without searching. Often used with --readouts 0"
+    )]
+    pub use_raw_scores: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "How many games to sample while searching for the next move. Unlimited if only --time_limit or --total_visits is given."
+    )]
+    pub readouts: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "Playout cap randomization: the fraction of moves that get a full search with --readouts and train the policy. The rest get a search with --cheap_readouts."
+    )]
+    pub full_search_fraction: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "Playout cap randomization: the readouts for moves that don't get a full search. Those moves are played, but aren't policy training targets."
+    )]
+    pub cheap_readouts: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "Stop searching for the next move after this many milliseconds."
+    )]
+    pub time_limit: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Stop searching for the next move once the root has been visited this many times, including visits from earlier moves."
+    )]
+    pub total_visits: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Stop searching for the next move as soon as the most visited action can no longer be overtaken within the other limits."
+    )]
+    pub stop_when_decided: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "Move selection is tempered to always chose the highest probability move after this ply of the game."
+    )]
+    pub tempering_point: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "The temperature T to sample the move to play from N^(1/T) with, by ply: <T>, step:<before>:<ply>:<after>, linear:<start>:<end>:<plies>, or exp:<start>:<decay>. Overrides --tempering_point."
+    )]
+    pub temperature: Option<TemperatureSchedule>,
+
+    #[structopt(
+        long,
+        help = "Noise coefficient for Dirichlet noise (what fraction of probability is from Dir(a))."
+    )]
+    pub noise_coefficient: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "This is a in Dir(a). Set this value proportional to the number of available actions for an average move of the game. #actions * dirichet_alpha ~= 10"
+    )]
+    pub dirichlet_alpha: Option<f64>,
+
+    #[structopt(
+        long,
+        help = "Mix Dirichlet noise into the priors of every node the search expands, as older versions of seraphim did, instead of only into the root's."
+    )]
+    pub noise_every_node: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "Score each newly expanded leaf with the expert's value head (q) instead of reading out to a terminal state."
+    )]
+    pub value_head: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "How many threads read out the search tree concurrently. Each thread adds a virtual loss to the edges it's reading through."
+    )]
+    pub threads: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "Share one node between every move order that reaches the same state, turning the search tree into a DAG."
+    )]
+    pub use_transpositions: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "Resign when the value of the root and of its best child are both below this threshold. Never resign if unset."
+    )]
+    pub resign_threshold: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "The fraction of games that are played out to the end without resigning, to measure how often resignation is wrong."
+    )]
+    pub resign_disabled_fraction: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "Keep searching on the opponent's time, e.g. while a human thinks about their move. Capped by --ponder_visits and --total_visits."
+    )]
+    pub ponder: Option<bool>,
+
+    #[structopt(
+        long,
+        help = "Stop pondering once the root has been visited this many times, so that the tree can't outgrow memory while the opponent thinks."
+    )]
+    pub ponder_visits: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "The value of actions that haven't been visited yet: draw, loss, or parent:<reduction> for the average value of the visited actions less the reduction."
+    )]
+    pub first_play_urgency: Option<FirstPlayUrgency>,
+
+    #[structopt(
+        long,
+        help = "Seed every random decision of the search, so that the same seed, model and options replay the same game. Needs --threads 1 and no --time_limit."
+    )]
+    pub seed: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Pick the move by Gumbel sequential halving over this many sampled root actions, and train on the completed-Q improved policy instead of the visit counts. Works with as few as 16 readouts."
+    )]
+    pub gumbel_actions: Option<usize>,
+
+    #[structopt(
+        long,
+        help = "c_visit in the Gumbel transformation of Q values, sigma(q) = (c_visit + max N) * c_scale * q. Enables Gumbel search."
+    )]
+    pub gumbel_c_visit: Option<f32>,
+
+    #[structopt(
+        long,
+        help = "c_scale in the Gumbel transformation of Q values. Enables Gumbel search."
+    )]
+    pub gumbel_c_scale: Option<f32>,
+}
+
+// KataGo's playout cap randomization. Most moves get a cheap search, which is enough to play them
+// with, and only the moves that get a full search are used to train the policy. More games are
+// generated for the same compute, and the value target gets more of them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayoutCap {
+    pub full_fraction: f32,  // The fraction of moves that get options.readouts
+    pub cheap_readouts: u32, // The readouts for the rest
+}
+impl std::default::Default for PlayoutCap {
+    // These defaults are the proportions used by KataGo.
+    fn default() -> Self {
+        Self {
+            full_fraction: 0.25,
+            cheap_readouts: 100,
+        }
+    }
+}
+
+// The AlphaZero exploration schedule. c grows slowly with the number of visits to the parent,
+// so that long searches keep exploring.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CpuctSchedule {
+    pub base: f32,
+    pub init: f32,
+}
+impl std::default::Default for CpuctSchedule {
+    // These defaults are the values used in the AlphaZero paper.
+    fn default() -> Self {
+        Self {
+            base: 19652.0,
+            init: 1.25,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchTreeOptions {
+    pub cpuct: f32,
+    pub cpuct_schedule: Option<CpuctSchedule>, // Replaces the constant cpuct when set
+    pub use_raw_scores: bool,
+    pub multi_pv: usize, // How many principal variations read_debug reports
+    pub readouts: u32,
+    pub playout_cap: Option<PlayoutCap>, // Gives some moves a cheap search that doesn't train the policy, when set
+    pub time_limit: Option<time::Duration>,
+    pub total_visits: Option<usize>,
+    pub stop_when_decided: bool,
+    pub tempering_point: u32,
+    pub temperature: Option<TemperatureSchedule>, // Replaces the switch at tempering_point when set
+    pub noise_coefficient: f32,
+    pub dirichlet_alpha: f64,
+    pub noise_every_node: bool, // The legacy behaviour. AGZ only adds noise at the root.
+    pub use_value_head: bool,
+    pub threads: usize,
+    pub use_transpositions: bool,
+    pub resign_threshold: Option<f32>,
+    pub resign_disabled_fraction: f32,
+    pub ponder: bool,
+    pub ponder_visits: usize, // Caps the root's total visits while pondering
+    pub first_play_urgency: FirstPlayUrgency,
+    pub seed: Option<u64>, // Seeds the search's random decisions. Drawn from the OS if unset.
+    pub gumbel: Option<GumbelOptions>, // Replaces PUCT at the root, and visit counts as the training target, when set
+}
+impl std::default::Default for SearchTreeOptions {
+    // These defaults are the values used in the AGZ paper.
+    fn default() -> Self {
+        Self {
+            use_raw_scores: false,
+            multi_pv: 3,
+            cpuct: 0.25,
+            cpuct_schedule: None,
+            readouts: 800,
+            playout_cap: None,
+            time_limit: None,
+            total_visits: None,
+            stop_when_decided: false,
+            tempering_point: 30,
+            temperature: None,
+            dirichlet_alpha: 0.03,
+            noise_coefficient: 0.25,
+            noise_every_node: false,
+            use_value_head: false,
+            threads: 1,
+            use_transpositions: false,
+            resign_threshold: None,
+            resign_disabled_fraction: 0.1,
+            ponder: false,
+            ponder_visits: 100_000,
+            first_play_urgency: FirstPlayUrgency::Draw,
+            seed: None,
+            gumbel: None,
+        }
+    }
+}
+impl SearchTreeOptions {
+    pub fn from_overrides(overrides: SearchTreeParamOverrides) -> Self {
+        let default = Self::default();
+        Self {
+            use_raw_scores: overrides.use_raw_scores.unwrap_or(default.use_raw_scores),
+            multi_pv: overrides.multi_pv.unwrap_or(default.multi_pv),
+            cpuct: overrides.cpuct.unwrap_or(default.cpuct),
+            cpuct_schedule: if overrides.cpuct_base.is_some() || overrides.cpuct_init.is_some() {
+                let alpha_zero = CpuctSchedule::default();
+                Some(CpuctSchedule {
+                    base: overrides.cpuct_base.unwrap_or(alpha_zero.base),
+                    init: overrides.cpuct_init.unwrap_or(alpha_zero.init),
+                })
+            } else {
+                default.cpuct_schedule
+            },
+            readouts: overrides.readouts.unwrap_or_else(|| {
+                if overrides.time_limit.is_some() || overrides.total_visits.is_some() {
+                    u32::MAX
+                } else {
+                    default.readouts
+                }
+            }),
+            playout_cap: if overrides.full_search_fraction.is_some()
+                || overrides.cheap_readouts.is_some()
+            {
+                let katago = PlayoutCap::default();
+                Some(PlayoutCap {
+                    full_fraction: overrides
+                        .full_search_fraction
+                        .unwrap_or(katago.full_fraction),
+                    cheap_readouts: overrides.cheap_readouts.unwrap_or(katago.cheap_readouts),
+                })
+            } else {
+                default.playout_cap
+            },
+            time_limit: overrides
+                .time_limit
+                .map(time::Duration::from_millis)
+                .or(default.time_limit),
+            total_visits: overrides.total_visits.or(default.total_visits),
+            stop_when_decided: overrides
+                .stop_when_decided
+                .unwrap_or(default.stop_when_decided),
+            tempering_point: overrides.tempering_point.unwrap_or(default.tempering_point),
+            temperature: overrides.temperature.or(default.temperature),
+            dirichlet_alpha: overrides.dirichlet_alpha.unwrap_or(default.dirichlet_alpha),
+            noise_coefficient: overrides
+                .noise_coefficient
+                .unwrap_or(default.noise_coefficient),
+            noise_every_node: overrides
+                .noise_every_node
+                .unwrap_or(default.noise_every_node),
+            use_value_head: overrides.value_head.unwrap_or(default.use_value_head),
+            threads: overrides.threads.unwrap_or(default.threads),
+            use_transpositions: overrides
+                .use_transpositions
+                .unwrap_or(default.use_transpositions),
+            resign_threshold: overrides.resign_threshold.or(default.resign_threshold),
+            resign_disabled_fraction: overrides
+                .resign_disabled_fraction
+                .unwrap_or(default.resign_disabled_fraction),
+            ponder: overrides.ponder.unwrap_or(default.ponder),
+            ponder_visits: overrides.ponder_visits.unwrap_or(default.ponder_visits),
+            first_play_urgency: overrides
+                .first_play_urgency
+                .unwrap_or(default.first_play_urgency),
+            seed: overrides.seed.or(default.seed),
+            gumbel: if overrides.gumbel_actions.is_some()
+                || overrides.gumbel_c_visit.is_some()
+                || overrides.gumbel_c_scale.is_some()
+            {
+                let paper = GumbelOptions::default();
+                Some(GumbelOptions {
+                    considered_actions: overrides
+                        .gumbel_actions
+                        .unwrap_or(paper.considered_actions),
+                    c_visit: overrides.gumbel_c_visit.unwrap_or(paper.c_visit),
+                    c_scale: overrides.gumbel_c_scale.unwrap_or(paper.c_scale),
+                })
+            } else {
+                default.gumbel
+            },
+        }
+    }
+
+    // The temperature to pick the move at ply with. Without a schedule, T = 1 before tempering_point and 0 after.
+    pub fn temperature(&self, ply: u32) -> f32 {
+        match self.temperature {
+            Some(schedule) => schedule.at(ply),
+            None if ply < self.tempering_point => 1.0,
+            None => 0.0,
+        }
+    }
+
+    // The limits that read and read_debug search with
+    pub fn limits(&self) -> SearchLimits {
+        SearchLimits {
+            readouts: Some(self.readouts as usize),
+            time: self.time_limit,
+            total_visits: self.total_visits,
+            stop_when_decided: self.stop_when_decided,
+        }
+    }
+
+    // The limits for pondering. Only a cap on the root's total visits applies, the smaller of ponder_visits
+    // and total_visits, so that a long think can't use up all the memory.
+    pub fn ponder_limits(&self) -> SearchLimits {
+        let cap = match self.total_visits {
+            Some(total_visits) => std::cmp::min(total_visits, self.ponder_visits),
+            None => self.ponder_visits,
+        };
+        SearchLimits {
+            total_visits: Some(cap),
+            ..Default::default()
+        }
+    }
+}
+
+// Every random decision of a search is drawn from one of these, so that a seeded search can be replayed.
+// Pcg32's output is specified, so the same seed gives the same games on every platform.
+type SearchRng = rand_pcg::Pcg32;
+
+// Bumped whenever the layout of SearchTreeSnapshot, or of anything in it, changes
+const SNAPSHOT_VERSION: u32 = 3;
+
+// Everything about a SearchTree except its inference engine and game, which are supplied again on load
+#[derive(Serialize, Deserialize)]
+struct SearchTreeSnapshot<State> {
+    options: SearchTreeOptions,
+    ply: u32,
+    root: usize,
+    resignation_enabled: bool,
+    resigned: bool,
+    would_have_resigned_at: Option<u32>,
+    rand: SearchRng, // So that a resumed seeded search goes on exactly as it would have
+    tree: TreeSnapshot<State>,
+}
+
+#[derive(Debug)]
+pub struct SearchTree<Inference, State, Game>
+where
+    Inference: inference::Inference,
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    inference: Inference,
+    rand: SearchRng,
+    search_tree: Tree<State>,
+    ply: u32,
+    root_idx: NodeIdx,
+    options: SearchTreeOptions,
+    game: Game,
+    resignation_enabled: bool, // False for the games that options.resign_disabled_fraction plays out to the end
+    resigned: bool,
+    would_have_resigned_at: Option<u32>, // The first ply at which the root fell below the resign threshold
+    gumbel_selection: Option<usize>,     // The action that the last Gumbel read picked
+}
+impl<Inference, State, Game> SearchTree<Inference, State, Game>
+where
+    // Readouts run on worker threads that borrow the engine
+    Inference: inference::Inference + Send,
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    // Start a new game that will be played by iterative searching
+    pub fn init_with_options(inference: Inference, game: Game, options: SearchTreeOptions) -> Self {
+        let search_tree = if options.use_transpositions {
+            Tree::with_transpositions()
+        } else {
+            Tree::new()
+        };
+        let root_idx = search_tree.add_root(State::default());
+        let mut rand = search_rng(&options);
+        let resignation_enabled = options.resign_threshold.is_some()
+            && rand.gen::<f32>() >= options.resign_disabled_fraction;
+
+        Self {
+            inference,
+            search_tree,
+            ply: 0,
+            options,
+            root_idx,
+            rand,
+            game,
+            resignation_enabled,
+            resigned: false,
+            would_have_resigned_at: None,
+            gumbel_selection: None,
+        }
+    }
+    pub fn init(inference: Inference, game: Game) -> Self {
+        Self::init_with_options(inference, game, SearchTreeOptions::default())
+    }
+
+    pub fn game_ref(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn current_state_ref(&self) -> &State {
+        self.search_tree[self.root_idx].state()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.resigned {
+            return GameStatus::PlayerToMoveResigned;
+        }
+        self.game.status(self.current_state_ref())
+    }
+
+    // Whether this game will resign when it falls below options.resign_threshold. False when there is no
+    // threshold, as well as for the games that resignation_disabled_for_game picks out.
+    pub fn resignation_enabled(&self) -> bool {
+        self.resignation_enabled
+    }
+
+    // Whether options.resign_disabled_fraction picked this game to be played out although a resign
+    // threshold is set. resignation_false_positive then tells whether resigning would have been a mistake.
+    pub fn resignation_disabled_for_game(&self) -> bool {
+        self.options.resign_threshold.is_some() && !self.resignation_enabled
+    }
+
+    // The first ply at which the search would have resigned, whether or not resignation is enabled
+    pub fn would_have_resigned_at(&self) -> Option<u32> {
+        self.would_have_resigned_at
+    }
+
+    // For a game that was played to the end with resignation disabled: whether the player who would have
+    // resigned went on to win or draw. None if nobody would have resigned, or the game isn't over.
+    pub fn resignation_false_positive(&self) -> Option<bool> {
+        let resign_ply = self.would_have_resigned_at?;
+        let last_player = (self.ply + 1) % 2; // The player who moved into the current position
+        let winner = match self.status() {
+            GameStatus::InProgress | GameStatus::PlayerToMoveResigned => return None,
+            GameStatus::LastPlayerWon => Some(last_player),
+            GameStatus::LastPlayerLost => Some(self.ply % 2),
+            GameStatus::Draw | GameStatus::NullResult => None,
+        };
+        // The resignation was right only if the resigning player's opponent won
+        Some(winner != Some((resign_ply + 1) % 2))
+    }
+
+    pub fn action_count(&self) -> usize {
+        self.game.action_count()
+    }
+
+    // The number of nodes in the search tree. Only the current position and its descendants are kept.
+    pub fn node_count(&self) -> usize {
+        self.search_tree.node_count()
+    }
+
+    // An estimate of the memory held by the search tree, not counting heap memory owned by states
+    pub fn memory_bytes(&self) -> usize {
+        self.search_tree.memory_bytes()
+    }
+
+    // How many times the search reached a state that was already in the tree, over the whole game.
+    // Always 0 unless use_transpositions is set.
+    pub fn transposition_hits(&self) -> usize {
+        self.search_tree.transposition_hits()
+    }
+
+    // Write the search tree, its statistics, and its options to path, so that the search can be resumed
+    // with load or inspected offline.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>
+    where
+        State: Serialize,
+    {
+        let snapshot = SearchTreeSnapshot {
+            options: self.options.clone(),
+            ply: self.ply,
+            root: self.root_idx.index(),
+            resignation_enabled: self.resignation_enabled,
+            resigned: self.resigned,
+            would_have_resigned_at: self.would_have_resigned_at,
+            rand: self.rand.clone(),
+            tree: self.search_tree.snapshot(),
+        };
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        bincode::serialize_into(&mut writer, &SNAPSHOT_VERSION)?;
+        bincode::serialize_into(&mut writer, &snapshot)?;
+        Ok(())
+    }
+
+    // Resume a search that was saved to path, with this inference engine and game
+    pub fn load<P: AsRef<Path>>(path: P, inference: Inference, game: Game) -> Result<Self>
+    where
+        State: DeserializeOwned,
+    {
+        let mut reader = BufReader::new(fs::File::open(path)?);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SearchError::IncompatibleSnapshot {
+                version,
+                expected: SNAPSHOT_VERSION,
+            }
+            .into());
+        }
+        let snapshot: SearchTreeSnapshot<State> = bincode::deserialize_from(&mut reader)?;
+        let search_tree = Tree::from_snapshot(snapshot.tree)?;
+        let root_idx = NodeIdx::new(snapshot.root);
+        if root_idx.index() >= search_tree.node_count() {
+            return Err(SearchError::MalformedSnapshot {
+                msg: format!(
+                    "The snapshot's root {} isn't one of its {} nodes",
+                    snapshot.root,
+                    search_tree.node_count()
+                ),
+            }
+            .into());
+        }
+        Ok(Self {
+            inference,
+            rand: snapshot.rand,
+            search_tree,
+            ply: snapshot.ply,
+            root_idx,
+            options: snapshot.options,
+            game,
+            resignation_enabled: snapshot.resignation_enabled,
+            resigned: snapshot.resigned,
+            would_have_resigned_at: snapshot.would_have_resigned_at,
+            gumbel_selection: None,
+        })
+    }
+
+    // The tree below the current root, down to options.max_depth and leaving out actions with fewer than
+    // options.min_visits visits. Write it out with write_dot or write_json to look at it.
+    pub fn export(&self, options: &export::ExportOptions) -> export::ExportedNode {
+        export::export(&self.search_tree, self.root_idx, &self.options, options)
+    }
+
+    // Read the next move and return the result without applying.
+    // Note: This call will typically be followed by apply() or apply_search_results()
+    pub fn read_debug(&mut self) -> Result<SearchResultsDebugInfo> {
+        let (limits, full) = self.playout_limits();
+        let mut debug = self.read_debug_with_limits(&limits)?;
+        debug.results.policy_target = full;
+        Ok(debug)
+    }
+
+    pub fn read_debug_with_limits(
+        &mut self,
+        limits: &SearchLimits,
+    ) -> Result<SearchResultsDebugInfo> {
+        let now = time::Instant::now();
+        let pre_read_transposition_hits = self.search_tree.transposition_hits();
+        let mut pre_read_visit_counts = HashMap::new();
+        for edge in self.search_tree[self.root_idx].edges() {
+            pre_read_visit_counts.insert(edge.action(), edge.visit_count());
+        }
+
+        let (readouts, limit) = self.readout(limits)?;
+
+        let root = &self.search_tree[self.root_idx];
+        let total_visit_count = root.visits();
+
+        let first_play_value = first_play_value(root, &self.options);
+        let mut candidates: Vec<CandidateActionDebugInformation> =
+            Vec::with_capacity(root.edges().len());
+        for edge in root.edges() {
+            let pre_read_visit_count = pre_read_visit_counts
+                .get(&edge.action())
+                .copied()
+                .unwrap_or(0);
+            let stimulus =
+                exploration_stimulus(&self.options, edge, total_visit_count, first_play_value);
+
+            let q = edge.total_value() / edge.visit_count() as f64;
+
+            candidates.push(CandidateActionDebugInformation {
+                action: edge.action(),
+                prior: edge.prior(),
+                raw: edge.raw_prior(),
+                posterior: (edge.visit_count() as f32) / (total_visit_count as f32),
+                total_value: edge.total_value(),
+                wins: edge.wins(),
+                losses: edge.losses(),
+                total_visits: edge.visit_count(),
+                visits_in_last_read: edge.visit_count() - pre_read_visit_count,
+                average_value: q,
+                exploration_stimulus: stimulus,
+                first_play_urgency: self.options.first_play_urgency,
+                first_play_value,
+            });
+        }
+        let principal_variations =
+            principal_variations(&self.search_tree, self.root_idx, self.options.multi_pv);
+        let temperature = self.options.temperature(self.ply);
+        let results = self.select();
+        let elapsed = now.elapsed();
+        Ok(SearchResultsDebugInfo {
+            time: limit::SearchTime {
+                elapsed,
+                readouts,
+                limit,
+            },
+            results,
+            temperature,
+            candidates,
+            transposition_hits: self.search_tree.transposition_hits() - pre_read_transposition_hits,
+            cpuct: cpuct(&self.options, total_visit_count),
+            principal_variations,
+        })
+    }
+
+    pub fn read(&mut self) -> Result<SearchResultsInfo> {
+        let (limits, full) = self.playout_limits();
+        let mut results = self.read_with_limits(&limits)?;
+        results.policy_target = full;
+        Ok(results)
+    }
+
+    // The limits for the next move: options.limits(), unless options.playout_cap picks a cheap search.
+    // Also returns whether the search is a full one.
+    fn playout_limits(&mut self) -> (SearchLimits, bool) {
+        let mut limits = self.options.limits();
+        match self.options.playout_cap {
+            Some(cap) if self.rand.gen::<f32>() >= cap.full_fraction => {
+                limits.readouts = Some(cap.cheap_readouts as usize);
+                (limits, false)
+            }
+            _ => (limits, true),
+        }
+    }
+
+    pub fn read_with_limits(&mut self, limits: &SearchLimits) -> Result<SearchResultsInfo> {
+        self.readout(limits)?;
+        Ok(self.select())
+    }
+    pub fn apply_search_results(&mut self, result: &SearchResultsInfo) {
+        if result.resign {
+            self.resigned = true;
+            return;
+        }
+        self.advance_to_node(result.application_token.0);
+    }
+
+    // update the search tree by applying an action.
+    pub fn apply(&mut self, action: usize) {
+        let next_node_idx = self.search_tree[self.root_idx]
+            .edges()
+            .iter()
+            .find(|edge| edge.action() == action)
+            .map(|edge| edge.child())
+            .unwrap();
+        self.advance_to_node(next_node_idx);
+    }
+
+    // Read out until one of the limits is reached, spread over options.threads workers.
+    // Returns how many readouts were done, and which limit stopped them.
+    fn readout(&mut self, limits: &SearchLimits) -> Result<(usize, LimitReached)> {
+        if let (Some(gumbel), false) = (self.options.gumbel, self.options.use_raw_scores) {
+            let noise = self.options.temperature(self.ply) > 0.0;
+            let (readouts, limit, selection) = gumbel::search(
+                &mut self.inference,
+                &self.search_tree,
+                self.root_idx,
+                &self.options,
+                &gumbel,
+                noise,
+                &self.game,
+                limits,
+                &mut self.rand,
+            )?;
+            self.gumbel_selection = selection;
+            return Ok((readouts, limit));
+        }
+        limits.validate()?;
+        let stop = AtomicBool::new(false);
+        read_out(
+            &mut self.inference,
+            &self.search_tree,
+            self.root_idx,
+            &self.options,
+            &self.game,
+            limits,
+            &stop,
+            None,
+            &mut self.rand,
+        )
+    }
+
+    // Keep reading out from the current root on other threads while wait runs on this one, e.g. while
+    // a human thinks about their move. The readouts stop when wait returns, or when they reach one of
+    // the limits. Without limits the tree grows for as long as wait runs, so pass ponder_limits unless
+    // something else bounds the wait. Applying the opponent's action afterwards keeps everything the
+    // readouts learned about that action's subtree.
+    pub fn ponder<T, F>(&mut self, limits: &SearchLimits, wait: F) -> Result<T>
+    where
+        F: FnOnce() -> T,
+    {
+        if self.status() != GameStatus::InProgress {
+            return Ok(wait());
+        }
+        // Stops the readouts even if wait panics, so that the scope below can be exited
+        struct StopOnDrop<'a>(&'a AtomicBool);
+        impl<'a> Drop for StopOnDrop<'a> {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let stop = AtomicBool::new(false);
+        let inference = &mut self.inference;
+        let tree = &self.search_tree;
+        let root_idx = self.root_idx;
+        let options = &self.options;
+        let game = &self.game;
+        let rand = &mut self.rand;
+        let stop_ref = &stop;
+        std::thread::scope(|scope| {
+            let pondering = scope.spawn(move || {
+                read_out(
+                    inference, tree, root_idx, options, game, limits, stop_ref, None, rand,
+                )
+            });
+            let result = {
+                let _stop = StopOnDrop(&stop);
+                wait()
+            };
+            let (readouts, limit) = pondering.join().expect("the pondering thread panicked")?;
+            debug!("Pondered {} readouts, stopped by {:?}", readouts, limit);
+            Ok(result)
+        })
+    }
+
+    // After sampling is done, it's time to select the next action that will actually be played.
+    // The AGZ algorithm uses tempering. Before tempering, it selects the next actions in proportion to the number of times each action was sampled.
+    // After tempering, it choses the next action with the highest number of samples. options.temperature generalizes
+    // that to any temperature schedule.
+    fn select(&mut self) -> SearchResultsInfo {
+        /*
+        Select a next move using the AGZ annealing method:
+
+            At the end of the search AGZ selects a move to play
+            in the root position proportional to its exponentiated visit count
+            p(a|s_0) = N(s_0, a)^{1/T} / \sum{b}N(s_0, b)^{1/T},
+            where T is a temperature parameter. The search tree is reused at subsequent
+            timesteps: the played action becomes the new root node, and other nodes are discarded.
+
+            AGZ resigns if its root value and best child value are lower than a threshhold. Seraphim does too, if
+            options.resign_threshold is set and this game wasn't picked to have resignation disabled.
+
+        Apply that selection to the search tree by advancing down the tree, and return the picked action so that the GameExpert will know what was picked.
+
+        The GameExpert should train itself to be more likely to pick the same value that the tree search picked. SearchResultsInfo
+        gives the label that should be trained.
+
+        TODO: Include win probability as a feature.
+        */
+
+        let would_resign = self.below_resign_threshold();
+        if would_resign && self.would_have_resigned_at.is_none() {
+            self.would_have_resigned_at = Some(self.ply);
+        }
+        let resign = would_resign && self.resignation_enabled;
+
+        if let (Some(gumbel), Some(action)) = (self.options.gumbel, self.gumbel_selection.take()) {
+            return self.select_gumbel(&gumbel, action, resign);
+        }
+
+        let max_actions = self.game.action_count();
+        let child_edges = self.search_tree[self.root_idx].edges();
+        if cfg!(debug_assertions) {
+            trace!("Edges during selection:");
+            for edge in child_edges {
+                trace!("{:?}", edge);
+            }
+        }
+        let mut results = vec![0.0; max_actions];
+
+        let total_visit_count = self.search_tree[self.root_idx].visits();
+
+        // The weight of each edge, in the order of child_edges, before the temperature is applied
+        let weights: Vec<f64> = if self.options.use_raw_scores {
+            child_edges
+                .iter()
+                .map(|edge| edge.raw_prior() as f64)
+                .collect()
+        } else {
+            child_edges
+                .iter()
+                .map(|edge| edge.visit_count() as f64)
+                .collect()
+        };
+        for (edge, weight) in child_edges.iter().zip(&weights) {
+            results[edge.action()] = if self.options.use_raw_scores {
+                *weight as f32
+            } else {
+                trace!("{} / {}", edge.visit_count(), total_visit_count);
+                (*weight / total_visit_count as f64) as f32
+            };
+        }
+
+        let temperature = self.options.temperature(self.ply);
+        let selected = sample_with_temperature(&weights, temperature, &mut self.rand)
+            .unwrap_or_else(|| {
+                (0..weights.len())
+                    .max_by(|&a, &b| {
+                        weights[a]
+                            .partial_cmp(&weights[b])
+                            .unwrap_or(std::cmp::Ordering::Less)
+                    })
+                    .unwrap()
+            });
+        let selected_edge = &child_edges[selected];
+        SearchResultsInfo {
+            results,
+            selection: selected_edge.action(),
+            application_token: ApplicationToken(selected_edge.child()),
+            resign,
+            policy_target: true,
+        }
+    }
+
+    // A Gumbel read trains on the improved policy, and plays the action that sequential halving picked.
+    // While the temperature is above 0 the read's Gumbel noise makes that a sample. After that the read
+    // leaves the noise out, so that it's the best of the actions the expert likes most.
+    fn select_gumbel(
+        &mut self,
+        gumbel: &GumbelOptions,
+        selection: usize,
+        resign: bool,
+    ) -> SearchResultsInfo {
+        let root = &self.search_tree[self.root_idx];
+        let legal_actions = self.game.legal_actions(root.state());
+        let results = gumbel::improved_policy(root, &legal_actions, gumbel);
+        let child = root
+            .edges()
+            .iter()
+            .find(|edge| edge.action() == selection)
+            .map(|edge| edge.child())
+            .unwrap();
+        SearchResultsInfo {
+            results,
+            selection,
+            application_token: ApplicationToken(child),
+            resign,
+            policy_target: true,
+        }
+    }
+
+    // Whether the root value and the value of the best child are both below options.resign_threshold,
+    // from the point of view of the player to move. As in AGZ, the root value is the expert's own q
+    // for the position, not the mean of the readouts below it, which would only echo the best child.
+    fn below_resign_threshold(&self) -> bool {
+        let threshold = match self.options.resign_threshold {
+            Some(threshold) => threshold as f64,
+            None => return false,
+        };
+        let root = &self.search_tree[self.root_idx];
+        let root_value = match root.value() {
+            Some(value) => value as f64,
+            None => return false,
+        };
+        let edges = root.edges();
+        let best_child_value = edges
+            .iter()
+            .filter(|edge| edge.visit_count() > 0)
+            .map(|edge| edge.total_value() / edge.visit_count() as f64)
+            .fold(f64::NEG_INFINITY, f64::max);
+        root_value < threshold && best_child_value < threshold
+    }
+
+    // The game can never return to positions that aren't below the new root, so those nodes are freed.
+    // If the new root was already expanded while it wasn't the root, it has no noise yet, so it gets some now.
+    fn advance_to_node(&mut self, node: NodeIdx) {
+        self.ply += 1;
+        let before = self.search_tree.node_count();
+        self.root_idx = self.search_tree.retain_subtree(node);
+        if !self.options.noise_every_node {
+            let options = &self.options;
+            let edges = self.search_tree[self.root_idx].edges_mut();
+            let sum: f32 = edges.iter().map(|edge| edge.raw_prior()).sum();
+            let mut priors: Vec<f32> = edges
+                .iter()
+                .map(|edge| scale_prior(edge.raw_prior(), sum, edges.len()))
+                .collect();
+            add_dirichlet_noise(&mut priors, options, &mut self.rand);
+            for (edge, prior) in edges.iter_mut().zip(priors) {
+                edge.set_prior(prior);
+            }
+        }
+        debug!(
+            "Advanced to ply {}. Kept {} of {} nodes ({} bytes)",
+            self.ply,
+            self.search_tree.node_count(),
+            before,
+            self.search_tree.memory_bytes()
+        );
+    }
+}
+
+/*
+    The next node to sample is the node that maximizes
+    exploration_stimulus = Q(s, a) + U(s, a)
+
+    where
+
+    U(s, a) = cP(s,a)sqrt(Nb)/(1 + Na)
+
+    Q(s, a) is the average reward for exploring that node in the past. It is equal to wins/nb
+
+    P is the prior probability that the action is the best
+    Na is the number of visits of to this edge,
+    Nb is the number of visits to the parent edge,
+    c is "a constant determining the level of exploration". With options.cpuct_schedule it isn't
+    constant: it grows with Nb, as in AlphaZero.
+
+    Readouts that are still in flight through an edge count towards Na, and count as losses in Q,
+    until they are backed up. This "virtual loss" makes concurrent readouts spread out over the tree
+    instead of all following the same path.
+
+    An action that hasn't been visited yet has no Q of its own. It's explored with the
+    first_play_value that options.first_play_urgency gives it instead.
+*/
+fn exploration_stimulus(
+    options: &SearchTreeOptions,
+    edge: &Edge,
+    parent_visits: usize,
+    first_play_value: f64,
+) -> f64 {
+    let N = parent_visits as f64; // how many times the parent state has been visited
+    let virtual_loss = edge.virtual_loss();
+    let n = (edge.visit_count() + virtual_loss) as f64 + 1.0f64; // how many times this action has been explored from the parent state
+
+    let p = edge.prior() as f64; // prior probability that this action is the best available
+    let total_value = if edge.visit_count() == 0 {
+        first_play_value
+    } else {
+        edge.total_value()
+    };
+    let q = (total_value - virtual_loss as f64) / n;
+
+    let c = cpuct(options, parent_visits);
+    let u = c * p * (N / n).sqrt();
+    if cfg!(debug_assertions) {
+        if (q + u).is_nan() {
+            warn!("Exploration is NAN! at {:?}", edge);
+        }
+    }
+    return q + u;
+}
+
+// c, at a node that has been visited parent_visits times
+fn cpuct(options: &SearchTreeOptions, parent_visits: usize) -> f64 {
+    match options.cpuct_schedule {
+        Some(schedule) => {
+            let base = schedule.base as f64;
+            ((parent_visits as f64 + base + 1.0) / base).ln() + schedule.init as f64
+        }
+        None => options.cpuct as f64,
+    }
+}
+
+// We always sample the edge that has the highest exploration_stimulus, as described above
+fn next_edge_to_sample<'a, State>(node: &'a Node<State>, options: &SearchTreeOptions) -> &'a Edge {
+    let parent_visits: usize = node.visits();
+    let first_play_value = first_play_value(node, options);
+
+    let explorations = node.edges().iter().map(|edge| {
+        (
+            edge,
+            exploration_stimulus(options, edge, parent_visits, first_play_value),
+        )
+    });
+
+    if cfg!(debug_assertions) {
+        for edge in node.edges() {
+            let stimulus = exploration_stimulus(options, edge, parent_visits, first_play_value);
+            trace!("{:?}\n{:?}: {:?}", edge, edge.child(), stimulus);
+        }
+    }
+
+    let (edge_with_max_stimulus, _) = explorations
+        .max_by(
+            |&(_, exploration_stimulus_a), &(_, exploration_stimulus_b)| {
+                exploration_stimulus_a
+                    .partial_cmp(&exploration_stimulus_b)
+                    .unwrap_or(::std::cmp::Ordering::Equal)
+            },
+        )
+        .unwrap();
+    edge_with_max_stimulus
+}
+
+// The principal variations after the root's k most visited actions. Each one follows the most visited
+// action down the tree until it reaches a node without visited actions, or goes round a cycle.
+fn principal_variations<State>(
+    tree: &Tree<State>,
+    root_idx: NodeIdx,
+    k: usize,
+) -> Vec<PrincipalVariation> {
+    let mut candidates: Vec<&Edge> = tree[root_idx]
+        .edges()
+        .iter()
+        .filter(|edge| edge.visit_count() > 0)
+        .collect();
+    candidates.sort_by_key(|edge| std::cmp::Reverse(edge.visit_count()));
+    candidates
+        .into_iter()
+        .take(k)
+        .map(|first| {
+            let mut moves = vec![];
+            let mut line = vec![root_idx];
+            let mut next = Some(first);
+            while let Some(edge) = next {
+                moves.push(PrincipalVariationMove {
+                    action: edge.action(),
+                    visits: edge.visit_count(),
+                    average_value: edge.total_value() / edge.visit_count() as f64,
+                });
+                if line.contains(&edge.child()) {
+                    break;
+                }
+                line.push(edge.child());
+                next = tree[edge.child()]
+                    .edges()
+                    .iter()
+                    .filter(|edge| edge.visit_count() > 0)
+                    .max_by_key(|edge| edge.visit_count());
+            }
+            PrincipalVariation { moves }
+        })
+        .collect()
+}
+
+// The value of this node's visited actions, averaged over their visits, for the player to move. None if
+// none have been visited.
+fn average_value<State>(node: &Node<State>) -> Option<f64> {
+    let visits: usize = node.edges().iter().map(|edge| edge.visit_count()).sum();
+    if visits == 0 {
+        return None;
+    }
+    let value: f64 = node.edges().iter().map(|edge| edge.total_value()).sum();
+    Some(value / visits as f64)
+}
+
+// The value that this node's unvisited actions are explored with
+fn first_play_value<State>(node: &Node<State>, options: &SearchTreeOptions) -> f64 {
+    match options.first_play_urgency {
+        FirstPlayUrgency::Draw => 0.0,
+        FirstPlayUrgency::Loss => -1.0,
+        FirstPlayUrgency::ParentMinus(reduction) => {
+            average_value(node).unwrap_or(0.0) - reduction as f64
+        }
+    }
+}
+
+fn search_rng(options: &SearchTreeOptions) -> SearchRng {
+    match options.seed {
+        Some(seed) => SearchRng::seed_from_u64(seed),
+        None => SearchRng::from_entropy(),
+    }
+}
+
+// Sample an index with probability weights[i]^(1/temperature) / sum_j weights[j]^(1/temperature). None if the
+// temperature is 0, which means taking the largest weight, or if no weight is positive.
+fn sample_with_temperature<R: Rng>(
+    weights: &[f64],
+    temperature: f32,
+    rng: &mut R,
+) -> Option<usize> {
+    if temperature <= 0.0 {
+        return None;
+    }
+    let max = weights.iter().cloned().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return None;
+    }
+    // Relative to the largest weight, so that low temperatures don't overflow
+    let exponent = 1.0 / temperature as f64;
+    let powers: Vec<f64> = weights
+        .iter()
+        .map(|&weight| (weight.max(0.0) / max).powf(exponent))
+        .collect();
+    let sum: f64 = powers.iter().sum();
+    let mut rand = rng.gen::<f64>() * sum;
+    for (i, power) in powers.iter().enumerate() {
+        if rand < *power {
+            return Some(i);
+        }
+        rand -= power;
+    }
+    // Only reached through rounding
+    powers.iter().rposition(|&power| power > 0.0)
+}
+
+// A raw prior, normalized so that the raw priors of a node's legal actions, which sum to sum, sum to 1.
+// If the expert gave them all 0, they're all equally likely.
+fn scale_prior(raw_prior: f32, sum: f32, legal_actions: usize) -> f32 {
+    if sum > 0.0 {
+        raw_prior / sum
+    } else {
+        1.0 / legal_actions as f32
+    }
+}
+
+// Mix Dirichlet noise into the priors of a node's legal actions, so that self-play tries moves the
+// expert wouldn't
+fn add_dirichlet_noise<R: Rng>(priors: &mut [f32], options: &SearchTreeOptions, rng: &mut R) {
+    let c = options.noise_coefficient;
+    // A Dirichlet distribution needs at least two outcomes
+    if c == 0.0 || priors.len() < 2 {
+        return;
+    }
+    let dist = Dirichlet::new_with_param(options.dirichlet_alpha, priors.len());
+    let sample = dist.sample(rng);
+    for (p, n) in priors.iter_mut().zip(sample) {
+        *p = (*p * (1.0 - c)) + (n as f32 * c);
+    }
+}
+
+// The value of a finished game from the point of view of the player who made the last move
+fn terminal_value(status: GameStatus) -> f64 {
+    match status {
+        GameStatus::LastPlayerWon | GameStatus::PlayerToMoveResigned => 1.0,
+        GameStatus::LastPlayerLost => -1.0,
+        _ => 0.0,
+    }
+}
+
+// Read out the tree from root_idx until one of the limits is reached, or until stop is set, spread over
+// options.threads workers. When root_action is given, every readout goes through that action of the root.
+// Returns how many readouts were done, and which limit stopped them.
+#[allow(clippy::too_many_arguments)]
+fn read_out<Inference, State, Game>(
+    inference: &mut Inference,
+    tree: &Tree<State>,
+    root_idx: NodeIdx,
+    options: &SearchTreeOptions,
+    game: &Game,
+    limits: &SearchLimits,
+    stop: &AtomicBool,
+    root_action: Option<usize>,
+    rng: &mut SearchRng,
+) -> Result<(usize, LimitReached)>
+where
+    Inference: inference::Inference + Send,
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    let started_at = time::Instant::now();
+    // The readout and total visit limits both come down to a number of readouts for this read
+    let root_visits = tree[root_idx].visits();
+    let budget = limits
+        .readouts
+        .map(|readouts| (readouts, LimitReached::Readouts))
+        .into_iter()
+        .chain(limits.total_visits.map(|total_visits| {
+            (
+                total_visits.saturating_sub(root_visits),
+                LimitReached::TotalVisits,
+            )
+        }))
+        .min_by_key(|(readouts, _)| *readouts);
+
+    let threads = std::cmp::max(options.threads, 1);
+
+    // Every worker needs its own inference engine to make progress concurrently. If the engine
+    // can't be cloned, the workers take turns with the one we have.
+    let mut forks: Vec<Inference> = (1..threads).filter_map(|_| inference.try_clone()).collect();
+    if forks.len() + 1 < threads {
+        debug!(
+            "{} threads are sharing {} inference engine(s)",
+            threads,
+            forks.len() + 1
+        );
+    }
+    let engines: Vec<Mutex<&mut Inference>> = std::iter::once(inference)
+        .chain(forks.iter_mut())
+        .map(Mutex::new)
+        .collect();
+
+    let readout = Readout {
+        tree,
+        root_idx,
+        root_action,
+        options,
+        game,
+        budget,
+        started_at,
+        deadline: limits.time.map(|time| started_at + time),
+        stop_when_decided: limits.stop_when_decided,
+        stop,
+        started: AtomicUsize::new(0),
+        completed: AtomicUsize::new(0),
+        limit_reached: Mutex::new(None),
+        failed: AtomicBool::new(false),
+        rng: Mutex::new(rng),
+    };
+    if threads == 1 {
+        readout.run(&engines[0])?;
+    } else {
+        let readout = &readout;
+        let engines = &engines;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|i| scope.spawn(move || readout.run(&engines[i % engines.len()])))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("a search thread panicked"))
+                .collect::<Result<Vec<()>>>()
+        })?;
+    }
+    let limit = readout
+        .limit_reached
+        .into_inner()
+        .unwrap()
+        .expect("every worker stops at a limit or an error");
+    Ok((readout.completed.into_inner(), limit))
+}
+
+// Everything the readout workers of a single read share. The tree's statistics are atomic,
+// so workers read out the tree concurrently without locking it.
+struct Readout<'a, State, Game>
+where
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    tree: &'a Tree<State>,
+    root_idx: NodeIdx,
+    root_action: Option<usize>, // Every readout goes through this action of the root, when it's given
+    options: &'a SearchTreeOptions,
+    game: &'a Game,
+    budget: Option<(usize, LimitReached)>, // The most readouts this read may do, and the limit that set it
+    started_at: time::Instant,
+    deadline: Option<time::Instant>,
+    stop_when_decided: bool,
+    stop: &'a AtomicBool,   // Set from outside the read to end it
+    started: AtomicUsize,   // How many readouts the workers have claimed
+    completed: AtomicUsize, // How many readouts have been backed up
+    limit_reached: Mutex<Option<LimitReached>>, // Set by the first worker to reach a limit, to stop the others
+    failed: AtomicBool,                         // Set when any worker fails, to stop the others
+    rng: Mutex<&'a mut SearchRng>,              // For the Dirichlet noise
+}
+
+impl<'a, State, Game> Readout<'a, State, Game>
+where
+    State: game::GameState,
+    Game: game::Game<State = State>,
+{
+    // Keep reading out until some worker reaches a limit, or until some worker fails.
+    fn run<Inference>(&self, inference: &Mutex<&mut Inference>) -> Result<()>
+    where
+        Inference: inference::Inference,
+    {
+        loop {
+            if self.failed.load(Ordering::SeqCst) || self.limit_reached.lock().unwrap().is_some() {
+                break;
+            }
+            if let Some(limit) = self.check_limits() {
+                self.reach(limit);
+                break;
+            }
+            let started = self.started.fetch_add(1, Ordering::SeqCst);
+            if let Some((budget, limit)) = self.budget {
+                if started >= budget {
+                    self.reach(limit);
+                    break;
+                }
+            }
+            if let Err(err) = self.read_to_end(inference) {
+                self.failed.store(true, Ordering::SeqCst);
+                return Err(err);
+            }
+            self.completed.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    fn reach(&self, limit: LimitReached) {
+        self.limit_reached.lock().unwrap().get_or_insert(limit);
+    }
+
+    // The stop flag, the time limit, or the early stop, if any of them has been reached. The readout
+    // budget is claimed separately, one readout at a time.
+    fn check_limits(&self) -> Option<LimitReached> {
+        if self.stop.load(Ordering::SeqCst) {
+            return Some(LimitReached::Stopped);
+        }
+        let now = time::Instant::now();
+        if let Some(deadline) = self.deadline {
+            if now >= deadline {
+                return Some(LimitReached::Time);
+            }
+        }
+        if self.stop_when_decided && self.decided(now) {
+            return Some(LimitReached::Decided);
+        }
+        None
+    }
+
+    // Whether the most visited action at the root would still be the most visited one after all
+    // of the readouts that the limits leave
+    fn decided(&self, now: time::Instant) -> bool {
+        let edges = self.tree[self.root_idx].edges();
+        if edges.len() == 1 {
+            return true;
+        }
+        let completed = self.completed.load(Ordering::SeqCst);
+        let by_budget = self
+            .budget
+            .map(|(budget, _)| budget.saturating_sub(completed));
+        // Without a readout budget, extrapolate the readouts left from the rate so far
+        let by_time = self.deadline.and_then(|deadline| {
+            let elapsed = (now - self.started_at).as_secs_f64();
+            if completed == 0 || elapsed == 0.0 {
+                return None;
+            }
+            let left = (deadline - now).as_secs_f64();
+            Some((completed as f64 / elapsed * left).ceil() as usize)
+        });
+        let remaining = match (by_budget, by_time) {
+            (Some(a), Some(b)) => std::cmp::min(a, b),
+            (Some(a), None) | (None, Some(a)) => a,
+            (None, None) => return false,
+        };
+        let mut visits: Vec<usize> = edges.iter().map(|edge| edge.visit_count()).collect();
+        if visits.len() < 2 {
+            return false;
+        }
+        visits.sort_unstable_by(|a, b| b.cmp(a));
+        visits[0] - visits[1] > remaining
+    }
+
+    // follow the search to a terminal node (A node where GameStatus is not InProgress),
+    // then back up the tree to the current analysis root (e.g., the state of the game as it has played out thus far),
+    // updating value and visit counts.
+    // When use_value_head is set, the readout instead stops at the first node it expands, and backs up
+    // the expert's q for that node.
+    fn read_to_end<Inference>(&self, inference: &Mutex<&mut Inference>) -> Result<()>
+    where
+        Inference: inference::Inference,
+    {
+        let tree = self.tree;
+        let mut path: Vec<(&Node<State>, &Edge)> = vec![];
+        let mut node_idx = self.root_idx;
+        let (value, status) = loop {
+            let node = &tree[node_idx];
+            let status = self.game.status(node.state());
+            if status != GameStatus::InProgress {
+                break (terminal_value(status), status);
+            }
+            let mut expansion = node.expansion();
+            if expansion == Expansion::Expanding {
+                // Another worker is waiting on the inference for this node. Starting over from the
+                // root would only lead back here, so wait for it, and carry on through its edges.
+                expansion = tree.wait_for_expansion(node_idx);
+            }
+            node.add_visit();
+            if expansion == Expansion::Unexpanded {
+                let expanding = match tree.begin_expansion(node_idx) {
+                    Some(expanding) => expanding,
+                    None => {
+                        // Another worker started expanding this node since we looked. Look again,
+                        // and wait for it.
+                        node.revert_visit();
+                        continue;
+                    }
+                };
+                let legal_actions = self.game.legal_actions(node.state());
+                let state_bytes = node.state().feature_bytes();
+                let noise = self.options.noise_every_node || node_idx == self.root_idx;
+                let pvalues = match self.pvalues(inference, &state_bytes[..], legal_actions, noise)
+                {
+                    Ok(pvalues) => pvalues,
+                    Err(err) => {
+                        // Dropping the claim puts the node back to unexpanded
+                        drop(expanding);
+                        node.revert_visit();
+                        Self::abandon(&path);
+                        return Err(err);
+                    }
+                };
+                let q = self.expand(expanding, pvalues);
+                if self.options.use_value_head && node_idx != self.root_idx {
+                    // q is the value for the player who is about to act in this node, but
+                    // the edge leading here belongs to the player who just acted.
+                    break (-q as f64, GameStatus::InProgress);
+                }
+            }
+            let edge = match self.root_action {
+                Some(action) if path.is_empty() => node
+                    .edges()
+                    .iter()
+                    .find(|edge| edge.action() == action)
+                    .expect("the root has an edge for the action it's forced through"),
+                _ => next_edge_to_sample(node, self.options),
+            };
+            edge.add_virtual_loss();
+            path.push((node, edge));
+            node_idx = edge.child();
+            if tree.uses_transpositions()
+                && path.iter().any(|(n, _)| std::ptr::eq(*n, &tree[node_idx]))
+            {
+                // The game repeated a position. Score it as a draw rather than going round the cycle.
+                break (0.0, GameStatus::Draw);
+            }
+        };
+        Self::backup(&path, value, status);
+        Ok(())
+    }
+
+    // record the result of a single readout up to the analysis root, which during normal operation
+    // is the node representating the current state of the game as it has evolved so far. In other words,
+    // it is the node we're starting our search from.
+    // value is from the point of view of the player who moved into the end of the path. It flips sign at each ply.
+    fn backup(path: &[(&Node<State>, &Edge)], value: f64, status: GameStatus) {
+        let mut win = match status {
+            GameStatus::LastPlayerWon | GameStatus::PlayerToMoveResigned => 1,
+            _ => 0,
+        };
+        let mut loss = match status {
+            GameStatus::LastPlayerLost => 1,
+            _ => 0,
+        };
+        let mut value = value;
+        for (_, edge) in path.iter().rev() {
+            edge.backup(value, win, loss);
+
+            value = -value;
+            std::mem::swap(&mut win, &mut loss);
+        }
+    }
+
+    // Undo the visits and virtual losses of a readout that won't be backed up
+    fn abandon(path: &[(&Node<State>, &Edge)]) {
+        for (node, edge) in path {
+            edge.revert_virtual_loss();
+            node.revert_visit();
+        }
+    }
+
+    fn pvalues<Inference>(
+        &self,
+        inference: &Mutex<&mut Inference>,
+        state_bytes: &[u8],
+        legal_actions: Vec<bool>,
+        noise: bool,
+    ) -> Result<PValues>
+    where
+        Inference: inference::Inference,
+    {
+        let raw_priors = inference.lock().unwrap().infer(state_bytes)?;
+        // A model for another game would otherwise index out of bounds in expand, with the node claimed
+        if raw_priors.ps.len() != legal_actions.len() {
+            return Err(InferenceError::WrongPriorCount {
+                expected: legal_actions.len(),
+                actual: raw_priors.ps.len(),
+            }
+            .into());
+        }
+        let legal_priors: Vec<f32> = raw_priors
+            .ps
+            .iter()
+            .zip(&legal_actions)
+            .filter(|(_, &l)| l)
+            .map(|(&p, _)| p)
+            .collect();
+        let sum: f32 = legal_priors.iter().sum();
+        let mut priors: Vec<f32> = legal_priors
+            .iter()
+            .map(|&p| scale_prior(p, sum, legal_priors.len()))
+            .collect();
+        if noise {
+            add_dirichlet_noise(&mut priors, self.options, &mut **self.rng.lock().unwrap());
+        }
+
+        let mut priors = priors.into_iter();
+        let noised_and_scaled_priors = legal_actions
+            .iter()
+            .map(|&l| if l { priors.next().unwrap() } else { 0.0 })
+            .collect();
+
+        Ok(PValues {
+            raw_priors,
+            noised_and_scaled_priors,
+            legal_actions,
+        })
+    }
+
+    // Add an edge and an unexpanded child for every legal action, and return the expert's q for this node
+    fn expand(&self, expanding: Expanding<'_, State>, pvalues: PValues) -> f32 {
+        let state = expanding.node().state();
+        let successors: Vec<Successor<State>> = pvalues
+            .noised_and_scaled_priors
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| pvalues.legal_actions[*i])
+            .map(|(i, p)| Successor {
+                action: i,
+                prior: *p,
+                raw_prior: pvalues.raw_priors.ps[i],
+                state: self.game.successor(state, i),
+            })
+            .collect();
+        expanding.finish(pvalues.raw_priors.q, successors);
+        pvalues.raw_priors.q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::tictactoe::TicTacToe;
+    use structopt::StructOpt;
+
+    // Gives the empty board a q of root_q, and every later position a q that favours whoever moved
+    // second, i.e. every move the first player can make looks lost.
+    #[derive(Debug)]
+    struct SecondPlayerWins {
+        root_q: f32,
+    }
+
+    impl inference::Inference for SecondPlayerWins {
+        fn infer(&mut self, input: &[u8]) -> Result<inference::Priors> {
+            let stones: u32 = input[..18].iter().map(|&b| u32::from(b)).sum();
+            let q = match stones {
+                0 => self.root_q,
+                // The first player has moved one more time than the second, who is to move
+                _ if stones % 2 == 1 => 0.95,
+                _ => -0.95,
+            };
+            Ok(inference::Priors {
+                ps: vec![1.0 / 9.0; 9],
+                q,
+            })
+        }
+    }
+
+    fn value_head_search<I: inference::Inference + Send>(
+        inference: I,
+    ) -> SearchTree<I, crate::tictactoe::State, TicTacToe> {
+        let options = SearchTreeOptions {
+            readouts: 32,
+            use_value_head: true,
+            resign_threshold: Some(-0.9),
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut search = SearchTree::init_with_options(inference, TicTacToe::new(), options);
+        search.read().unwrap();
+        search
+    }
+
+    #[test]
+    fn resigning_needs_the_expert_to_agree_that_the_root_is_lost() {
+        // The readouts back up the same losing value either way, so only the root's own q differs
+        let search = value_head_search(SecondPlayerWins { root_q: 0.0 });
+        let root = &search.search_tree[search.root_idx];
+        assert!(average_value(root).unwrap() < -0.9);
+        assert!(!search.below_resign_threshold());
+
+        let search = value_head_search(SecondPlayerWins { root_q: -0.95 });
+        assert!(search.below_resign_threshold());
+    }
+
+    #[test]
+    fn each_first_play_urgency_values_an_unvisited_edge() {
+        let tree = Tree::new();
+        let root = tree.add_root(0u32);
+        let successors = (1..4).map(|state| Successor {
+            action: state as usize,
+            prior: 1.0 / 3.0,
+            raw_prior: 1.0 / 3.0,
+            state,
+        });
+        tree.begin_expansion(root).unwrap().finish(0.0, successors);
+        let edges = tree[root].edges();
+        for &(edge, value) in &[(0, 0.5), (1, -0.1), (1, -0.1)] {
+            edges[edge].add_virtual_loss();
+            edges[edge].backup(value, 0, 0);
+        }
+        let unvisited = &edges[2];
+
+        // The visited actions average (0.5 - 0.1 - 0.1) / 3 = 0.1
+        for &(flag, expected) in &[("draw", 0.0), ("loss", -1.0), ("parent:0.25", -0.15)] {
+            let overrides = SearchTreeParamOverrides::from_iter(&[
+                "search",
+                "--first_play_urgency",
+                flag,
+                "--cpuct",
+                "0",
+            ]);
+            let options = SearchTreeOptions::from_overrides(overrides);
+            let value = first_play_value(&tree[root], &options);
+            assert!((value - expected).abs() < 1e-9, "{}: {}", flag, value);
+            // Without an exploration bonus, the edge is explored with exactly that value
+            let stimulus = exploration_stimulus(&options, unvisited, 3, value);
+            assert!((stimulus - expected).abs() < 1e-9, "{}: {}", flag, stimulus);
+        }
+    }
+
+    #[test]
+    fn a_cold_gumbel_search_plays_the_winner_of_sequential_halving() {
+        let options = SearchTreeOptions {
+            readouts: 16,
+            use_value_head: true,
+            tempering_point: 0,
+            seed: Some(5),
+            gumbel: Some(GumbelOptions::default()),
+            ..Default::default()
+        };
+        let mut search = SearchTree::init_with_options(
+            SecondPlayerWins { root_q: 0.0 },
+            TicTacToe::new(),
+            options,
+        );
+        let limits = search.options.limits();
+        search.readout(&limits).unwrap();
+        assert!(search.gumbel_selection.is_some());
+
+        // Even an action that the improved policy likes least is played if halving picked it
+        let root = &search.search_tree[search.root_idx];
+        let policy = gumbel::improved_policy(
+            root,
+            &search.game.legal_actions(root.state()),
+            &GumbelOptions::default(),
+        );
+        let worst = (0..9)
+            .min_by(|&a, &b| policy[a].partial_cmp(&policy[b]).unwrap())
+            .unwrap();
+        search.gumbel_selection = Some(worst);
+        assert_eq!(search.select().selection, worst);
+    }
+
+    // Uniform priors and an even position, but slow enough that workers reach the same node while
+    // its inference is still running.
+    #[derive(Debug)]
+    struct Slow;
+
+    impl inference::Inference for Slow {
+        fn infer(&mut self, _input: &[u8]) -> Result<inference::Priors> {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            Ok(inference::Priors {
+                ps: vec![1.0 / 9.0; 9],
+                q: 0.0,
+            })
+        }
+
+        fn try_clone(&self) -> Option<Self> {
+            Some(Slow)
+        }
+    }
+
+    #[test]
+    fn workers_that_meet_at_an_expansion_each_back_up_one_readout() {
+        let options = SearchTreeOptions {
+            readouts: 200,
+            threads: 4,
+            use_value_head: true,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut search = SearchTree::init_with_options(Slow, TicTacToe::new(), options);
+        let limits = search.options.limits();
+        let (completed, _) = search.readout(&limits).unwrap();
+        assert_eq!(completed, 200);
+
+        // No readout was lost or counted twice, and none is left in flight
+        let tree = &search.search_tree;
+        let root = &tree[search.root_idx];
+        assert_eq!(root.visits(), 200);
+        let edges = root.edges();
+        assert_eq!(
+            edges.iter().map(|edge| edge.visit_count()).sum::<usize>(),
+            200
+        );
+        for edge in edges {
+            assert_eq!(edge.virtual_loss(), 0);
+            assert_eq!(tree[edge.child()].visits(), edge.visit_count());
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Keeps track of how often resignation would have been wrong.
+//! AGZ plays a fraction of its self-play games to the end with resignation disabled, and keeps its
+//! resign threshold low enough that fewer than 5% of those games would have been resigned by a
+//! player who went on to win or draw. Record every finished game here to get the same number.
+use crate::{game, inference, search::SearchTree};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResignationStats {
+    pub games: usize,                 // Finished games recorded
+    pub resignations: usize,          // Games that ended by resignation
+    pub disabled_games: usize,        // Games played to the end with resignation disabled
+    pub disabled_would_resign: usize, // Disabled games in which a player fell below the resign threshold
+    pub false_positives: usize,       // Disabled games in which that player went on to win or draw
+}
+
+impl ResignationStats {
+    pub fn record<Inference, State, Game>(&mut self, search: &SearchTree<Inference, State, Game>)
+    where
+        Inference: inference::Inference + Send,
+        State: game::GameState,
+        Game: game::Game<State = State>,
+    {
+        self.games += 1;
+        if search.status() == game::GameStatus::PlayerToMoveResigned {
+            self.resignations += 1;
+        }
+        if search.resignation_disabled_for_game() {
+            self.disabled_games += 1;
+            if let Some(false_positive) = search.resignation_false_positive() {
+                self.disabled_would_resign += 1;
+                if false_positive {
+                    self.false_positives += 1;
+                }
+            }
+        }
+    }
+
+    // The fraction of would-be resignations in disabled games that were wrong
+    pub fn false_positive_rate(&self) -> Option<f64> {
+        if self.disabled_would_resign == 0 {
+            return None;
+        }
+        Some(self.false_positives as f64 / self.disabled_would_resign as f64)
+    }
+}
+
+impl std::fmt::Display for ResignationStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} / {} games resigned. {} / {} games with resignation disabled would have resigned, {} wrongly",
+            self.resignations,
+            self.games,
+            self.disabled_would_resign,
+            self.disabled_games,
+            self.false_positives
+        )?;
+        if let Some(rate) = self.false_positive_rate() {
+            write!(f, " ({:.1}%)", rate * 100.0)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! How much randomness goes into the choice of the move to play. The search plays action a with
+//! probability N(a)^(1/T) / sum_b N(b)^(1/T). At T = 1 that's in proportion to the visit counts, and as
+//! T goes to 0 it becomes the most visited action. Early moves are usually played hot, for variety in
+//! self-play, and later ones cold.
+use serde::{Deserialize, Serialize};
+
+use crate::error::SearchError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TemperatureSchedule {
+    Constant(f32),
+    // before until ply, then after. AGZ plays T = 1 for 30 plies, then T = 0.
+    Step { before: f32, ply: u32, after: f32 },
+    // From start at ply 0 to end at ply plies, then end
+    Linear { start: f32, end: f32, plies: u32 },
+    // start * decay^ply
+    Exponential { start: f32, decay: f32 },
+}
+
+impl TemperatureSchedule {
+    // The temperature to play the move at ply with
+    pub fn at(&self, ply: u32) -> f32 {
+        match *self {
+            TemperatureSchedule::Constant(temperature) => temperature,
+            TemperatureSchedule::Step {
+                before,
+                ply: step,
+                after,
+            } => {
+                if ply < step {
+                    before
+                } else {
+                    after
+                }
+            }
+            TemperatureSchedule::Linear { start, end, plies } => {
+                if ply >= plies {
+                    end
+                } else {
+                    start + (end - start) * ply as f32 / plies as f32
+                }
+            }
+            TemperatureSchedule::Exponential { start, decay } => start * decay.powi(ply as i32),
+        }
+    }
+}
+
+impl std::str::FromStr for TemperatureSchedule {
+    type Err = SearchError;
+    // "<T>", "step:<before>:<ply>:<after>", "linear:<start>:<end>:<plies>", or "exp:<start>:<decay>"
+    fn from_str(s: &str) -> std::result::Result<Self, SearchError> {
+        let invalid = || SearchError::InvalidConfiguration {
+            msg: format!(
+                "Unknown temperature schedule '{}'. Expected <T>, step:<before>:<ply>:<after>, \
+                 linear:<start>:<end>:<plies>, or exp:<start>:<decay>",
+                s
+            ),
+        };
+        let parts: Vec<&str> = s.split(':').collect();
+        let float = |i: usize| parts[i].parse::<f32>().map_err(|_| invalid());
+        let ply = |i: usize| parts[i].parse::<u32>().map_err(|_| invalid());
+        match (parts[0], parts.len()) {
+            ("step", 4) => Ok(TemperatureSchedule::Step {
+                before: float(1)?,
+                ply: ply(2)?,
+                after: float(3)?,
+            }),
+            ("linear", 4) => Ok(TemperatureSchedule::Linear {
+                start: float(1)?,
+                end: float(2)?,
+                plies: ply(3)?,
+            }),
+            ("exp", 3) => Ok(TemperatureSchedule::Exponential {
+                start: float(1)?,
+                decay: float(2)?,
+            }),
+            (_, 1) => float(0).map(TemperatureSchedule::Constant),
+            _ => Err(invalid()),
+        }
+    }
+}
@@ -0,0 +1,791 @@
+//! The search tree. Nodes live in a lock-free arena and are addressed by stable indices.
+//! Each node owns a contiguous array of its outgoing edges and a pointer to its parent.
+//! All of the statistics that readouts update are atomic, so any number of threads can
+//! read out the same tree at once through a shared reference.
+//!
+//! A tree can optionally be built with a transposition table. Then a successor state that's
+//! already in the tree gets an edge to the existing node instead of a fresh node, so positions
+//! reached by different move orders share one node, one inference, and one set of statistics,
+//! and the tree becomes a DAG.
+//!
+//! A TreeSnapshot is a plain copy of a tree's nodes and statistics, for serializing it.
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::UnsafeCell,
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+};
+
+use super::arena::Arena;
+use crate::error::{Result, SearchError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeIdx(usize);
+
+impl NodeIdx {
+    pub(crate) fn new(idx: usize) -> Self {
+        NodeIdx(idx)
+    }
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expansion {
+    Unexpanded,
+    Expanding, // A readout is waiting on the inference for this node
+    Expanded,
+}
+
+const UNEXPANDED: u8 = 0;
+const EXPANDING: u8 = 1;
+const EXPANDED: u8 = 2;
+
+#[derive(Debug)]
+pub struct Edge {
+    action: usize, // The action that this edge represents.
+    prior: f32,    // The effective prior, after scaling and Dirichlet noise.
+    raw_prior: f32,
+    child: NodeIdx,
+    visit_count: AtomicUsize,  // # games played from this position
+    virtual_loss: AtomicUsize, // # readouts currently in flight through this edge
+    total_value: AtomicU64, // The bits of an f64. W(s, a) in the AGZ paper. Q(s, a) = total_value / visit_count.
+    wins: AtomicUsize,      // Readouts from this position that ended in a terminal win
+    losses: AtomicUsize,    // Readouts from this position that ended in a terminal loss
+}
+
+impl Edge {
+    fn new(action: usize, prior: f32, raw_prior: f32, child: NodeIdx) -> Self {
+        Edge {
+            action,
+            prior,
+            raw_prior,
+            child,
+            visit_count: AtomicUsize::new(0),
+            virtual_loss: AtomicUsize::new(0),
+            total_value: AtomicU64::new(0.0f64.to_bits()),
+            wins: AtomicUsize::new(0),
+            losses: AtomicUsize::new(0),
+        }
+    }
+    pub fn action(&self) -> usize {
+        self.action
+    }
+    pub fn prior(&self) -> f32 {
+        self.prior
+    }
+    pub fn raw_prior(&self) -> f32 {
+        self.raw_prior
+    }
+    pub fn set_prior(&mut self, prior: f32) {
+        self.prior = prior;
+    }
+    pub fn child(&self) -> NodeIdx {
+        self.child
+    }
+    pub fn visit_count(&self) -> usize {
+        self.visit_count.load(Ordering::Relaxed)
+    }
+    pub fn virtual_loss(&self) -> usize {
+        self.virtual_loss.load(Ordering::Relaxed)
+    }
+    pub fn total_value(&self) -> f64 {
+        f64::from_bits(self.total_value.load(Ordering::Relaxed))
+    }
+    pub fn wins(&self) -> usize {
+        self.wins.load(Ordering::Relaxed)
+    }
+    pub fn losses(&self) -> usize {
+        self.losses.load(Ordering::Relaxed)
+    }
+
+    // Mark a readout as in flight through this edge
+    pub fn add_virtual_loss(&self) {
+        self.virtual_loss.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Take back the virtual loss of a readout that won't be backed up
+    pub fn revert_virtual_loss(&self) {
+        self.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Record a finished readout through this edge, and take back its virtual loss
+    pub fn backup(&self, value: f64, wins: usize, losses: usize) {
+        let _ = self
+            .total_value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+        self.wins.fetch_add(wins, Ordering::Relaxed);
+        self.losses.fetch_add(losses, Ordering::Relaxed);
+        self.visit_count.fetch_add(1, Ordering::Relaxed);
+        self.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct Node<State> {
+    state: State,
+    parent: Option<NodeIdx>, // With a transposition table, the first parent the node was reached from
+    visits: AtomicUsize,
+    expansion: AtomicU8,
+    // Written once, by the thread that moves expansion from EXPANDING to EXPANDED
+    edges: UnsafeCell<Box<[Edge]>>,
+    value: UnsafeCell<f32>, // The expert's q for this state, for the player to move
+}
+
+// Ok, because edges and value are only written while expansion is EXPANDING, by the one thread that
+// won the race to set it, and only read after that thread has published EXPANDED.
+unsafe impl<State: Sync> Sync for Node<State> {}
+
+impl<State> Node<State> {
+    fn new_unexpanded(state: State, parent: Option<NodeIdx>) -> Self {
+        Node {
+            state,
+            parent,
+            visits: AtomicUsize::new(0),
+            expansion: AtomicU8::new(UNEXPANDED),
+            edges: UnsafeCell::new(Box::new([])),
+            value: UnsafeCell::new(0.0),
+        }
+    }
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+    pub fn parent(&self) -> Option<NodeIdx> {
+        self.parent
+    }
+    pub fn visits(&self) -> usize {
+        self.visits.load(Ordering::Relaxed)
+    }
+    pub fn add_visit(&self) {
+        self.visits.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn revert_visit(&self) {
+        self.visits.fetch_sub(1, Ordering::Relaxed);
+    }
+    pub fn expansion(&self) -> Expansion {
+        match self.expansion.load(Ordering::Acquire) {
+            UNEXPANDED => Expansion::Unexpanded,
+            EXPANDING => Expansion::Expanding,
+            _ => Expansion::Expanded,
+        }
+    }
+
+    // One edge per legal action, in the order they were expanded. Empty until the node is expanded.
+    pub fn edges(&self) -> &[Edge] {
+        if self.expansion.load(Ordering::Acquire) == EXPANDED {
+            unsafe { &*self.edges.get() }
+        } else {
+            &[]
+        }
+    }
+
+    // The expert's q for this state, from the point of view of the player to move. None until the node
+    // is expanded.
+    pub fn value(&self) -> Option<f32> {
+        if self.expansion.load(Ordering::Acquire) == EXPANDED {
+            Some(unsafe { *self.value.get() })
+        } else {
+            None
+        }
+    }
+
+    pub fn edges_mut(&mut self) -> &mut [Edge] {
+        self.edges.get_mut()
+    }
+}
+
+impl<State> std::fmt::Debug for Node<State>
+where
+    State: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Node")
+            .field("state", &self.state)
+            .field("parent", &self.parent)
+            .field("visits", &self.visits())
+            .field("expansion", &self.expansion())
+            .field("edges", &self.edges())
+            .finish()
+    }
+}
+
+// A child to add to a node when it is expanded
+#[derive(Debug)]
+pub struct Successor<State> {
+    pub action: usize,
+    pub prior: f32,
+    pub raw_prior: f32,
+    pub state: State,
+}
+
+pub struct Tree<State> {
+    nodes: Arena<Node<State>>,
+    edge_count: AtomicUsize,
+    transpositions: Option<Mutex<HashMap<State, NodeIdx>>>,
+    transposition_hits: AtomicUsize, // How many successors were found in the transposition table
+    // Notified whenever an expansion ends, for the readouts that are waiting on it. Expansions are
+    // only waited on when two readouts reach the same new node at once, so one lock does for the tree.
+    expansion_ended: Condvar,
+    expansion_lock: Mutex<()>,
+}
+
+impl<State> Tree<State> {
+    pub fn new() -> Self {
+        Tree {
+            nodes: Arena::new(),
+            edge_count: AtomicUsize::new(0),
+            transpositions: None,
+            transposition_hits: AtomicUsize::new(0),
+            expansion_ended: Condvar::new(),
+            expansion_lock: Mutex::new(()),
+        }
+    }
+
+    // A tree that shares one node between every path that reaches the same state
+    pub fn with_transpositions() -> Self {
+        Tree {
+            transpositions: Some(Mutex::new(HashMap::new())),
+            ..Self::new()
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn uses_transpositions(&self) -> bool {
+        self.transpositions.is_some()
+    }
+
+    // How many times an expansion found a successor that was already in the tree
+    pub fn transposition_hits(&self) -> usize {
+        self.transposition_hits.load(Ordering::Relaxed)
+    }
+
+    // The bytes allocated for nodes, edges, and the transposition table. Doesn't count heap memory
+    // owned by the states.
+    pub fn memory_bytes(&self) -> usize {
+        let transpositions = self
+            .transpositions
+            .as_ref()
+            .map(|table| table.lock().unwrap().capacity() * std::mem::size_of::<(State, NodeIdx)>())
+            .unwrap_or(0);
+        self.nodes.capacity_bytes()
+            + self.edge_count.load(Ordering::Relaxed) * std::mem::size_of::<Edge>()
+            + transpositions
+    }
+
+    // Free every node that isn't root or one of its descendants, and return root's new index.
+    // The retained nodes are renumbered, so every NodeIdx from before the call is invalidated.
+    pub fn retain_subtree(&mut self, root: NodeIdx) -> NodeIdx
+    where
+        State: Hash + Eq + Clone,
+    {
+        let mut nodes: Vec<Option<Node<State>>> = std::mem::take(&mut self.nodes)
+            .into_vec()
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        // Number the retained nodes breadth first, starting from the root. Each node's new parent
+        // is the retained node it was first reached from.
+        let mut renumbered: Vec<Option<NodeIdx>> = vec![None; nodes.len()];
+        let mut retained = vec![(root, None)];
+        renumbered[root.0] = Some(NodeIdx(0));
+        let mut i = 0;
+        while i < retained.len() {
+            let (old_idx, _) = retained[i];
+            for edge in nodes[old_idx.0].as_ref().unwrap().edges() {
+                if renumbered[edge.child.0].is_none() {
+                    renumbered[edge.child.0] = Some(NodeIdx(retained.len()));
+                    retained.push((edge.child, Some(NodeIdx(i))));
+                }
+            }
+            i += 1;
+        }
+
+        let mut edge_count = 0;
+        let mut transpositions = HashMap::new();
+        for (old_idx, parent) in retained {
+            let mut node = nodes[old_idx.0].take().unwrap();
+            node.parent = parent;
+            for edge in node.edges_mut() {
+                edge.child = renumbered[edge.child.0].unwrap();
+            }
+            edge_count += node.edges_mut().len();
+            if self.transpositions.is_some() {
+                transpositions.insert(node.state.clone(), NodeIdx(self.nodes.len()));
+            }
+            self.nodes.push(node);
+        }
+        *self.edge_count.get_mut() = edge_count;
+        if let Some(table) = self.transpositions.as_mut() {
+            *table.get_mut().unwrap() = transpositions;
+        }
+        NodeIdx(0)
+    }
+
+    // Claim the right to expand this node. Returns None if the node is already expanded, or if another
+    // thread claimed it first. If the returned Expanding is dropped without being finished, the node
+    // goes back to being unexpanded.
+    pub fn begin_expansion(&self, idx: NodeIdx) -> Option<Expanding<'_, State>> {
+        self[idx]
+            .expansion
+            .compare_exchange(UNEXPANDED, EXPANDING, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Expanding {
+                tree: self,
+                idx,
+                finished: false,
+            })
+    }
+
+    // Block while another thread is expanding this node. Returns Expanded, or Unexpanded if that
+    // thread gave up, e.g. because its inference failed.
+    pub fn wait_for_expansion(&self, idx: NodeIdx) -> Expansion {
+        let mut lock = self.expansion_lock.lock().unwrap();
+        loop {
+            match self[idx].expansion() {
+                Expansion::Expanding => lock = self.expansion_ended.wait(lock).unwrap(),
+                expansion => return expansion,
+            }
+        }
+    }
+
+    // Wake the waiters once a node's expansion has been stored. Taking the lock first means that a
+    // waiter is either still to check the node, or already asleep.
+    fn end_expansion(&self, idx: NodeIdx, expansion: u8) {
+        self[idx].expansion.store(expansion, Ordering::Release);
+        drop(self.expansion_lock.lock().unwrap());
+        self.expansion_ended.notify_all();
+    }
+}
+
+impl<State> Tree<State>
+where
+    State: Hash + Eq + Clone,
+{
+    // Copy the tree's nodes and statistics. Readouts that are in flight aren't part of the snapshot,
+    // and nor are nodes that are being expanded.
+    pub fn snapshot(&self) -> TreeSnapshot<State> {
+        let nodes = (0..self.nodes.len())
+            .map(|i| {
+                let node = self.nodes.get(i);
+                NodeSnapshot {
+                    state: node.state.clone(),
+                    parent: node.parent.map(|parent| parent.0),
+                    visits: node.visits(),
+                    expanded: node.value().map(|value| {
+                        let edges = node
+                            .edges()
+                            .iter()
+                            .map(|edge| EdgeSnapshot {
+                                action: edge.action,
+                                prior: edge.prior,
+                                raw_prior: edge.raw_prior,
+                                child: edge.child.0,
+                                visit_count: edge.visit_count(),
+                                total_value: edge.total_value(),
+                                wins: edge.wins(),
+                                losses: edge.losses(),
+                            })
+                            .collect();
+                        (value, edges)
+                    }),
+                }
+            })
+            .collect();
+        TreeSnapshot {
+            nodes,
+            uses_transpositions: self.uses_transpositions(),
+            transposition_hits: self.transposition_hits(),
+        }
+    }
+
+    // Rebuild a tree from a snapshot, including its transposition table if it had one. Fails if any of
+    // its nodes refers to a node that isn't in the snapshot.
+    pub fn from_snapshot(snapshot: TreeSnapshot<State>) -> Result<Self> {
+        let node_count = snapshot.nodes.len();
+        for (i, node) in snapshot.nodes.iter().enumerate() {
+            let children = node
+                .expanded
+                .iter()
+                .flat_map(|(_, edges)| edges.iter().map(|edge| edge.child));
+            if let Some(missing) = node
+                .parent
+                .into_iter()
+                .chain(children)
+                .find(|&idx| idx >= node_count)
+            {
+                return Err(SearchError::MalformedSnapshot {
+                    msg: format!(
+                        "Node {} refers to node {}, but there are only {} nodes",
+                        i, missing, node_count
+                    ),
+                }
+                .into());
+            }
+        }
+        let mut tree = if snapshot.uses_transpositions {
+            Self::with_transpositions()
+        } else {
+            Self::new()
+        };
+        let mut edge_count = 0;
+        let mut transpositions = HashMap::new();
+        for node in snapshot.nodes {
+            if tree.transpositions.is_some() {
+                transpositions.insert(node.state.clone(), NodeIdx(tree.nodes.len()));
+            }
+            let mut restored = Node::new_unexpanded(node.state, node.parent.map(NodeIdx));
+            *restored.visits.get_mut() = node.visits;
+            if let Some((value, edges)) = node.expanded {
+                edge_count += edges.len();
+                let edges: Vec<Edge> = edges
+                    .into_iter()
+                    .map(|e| {
+                        let edge = Edge::new(e.action, e.prior, e.raw_prior, NodeIdx(e.child));
+                        edge.visit_count.store(e.visit_count, Ordering::Relaxed);
+                        edge.total_value
+                            .store(e.total_value.to_bits(), Ordering::Relaxed);
+                        edge.wins.store(e.wins, Ordering::Relaxed);
+                        edge.losses.store(e.losses, Ordering::Relaxed);
+                        edge
+                    })
+                    .collect();
+                *restored.edges.get_mut() = edges.into_boxed_slice();
+                *restored.value.get_mut() = value;
+                *restored.expansion.get_mut() = EXPANDED;
+            }
+            tree.nodes.push(restored);
+        }
+        *tree.edge_count.get_mut() = edge_count;
+        *tree.transposition_hits.get_mut() = snapshot.transposition_hits;
+        if let Some(table) = tree.transpositions.as_mut() {
+            *table.get_mut().unwrap() = transpositions;
+        }
+        Ok(tree)
+    }
+
+    // Add a node with no parent
+    pub fn add_root(&self, state: State) -> NodeIdx {
+        match &self.transpositions {
+            Some(table) => {
+                let mut table = table.lock().unwrap();
+                if let Some(&idx) = table.get(&state) {
+                    return idx;
+                }
+                let idx = NodeIdx(self.nodes.push(Node::new_unexpanded(state.clone(), None)));
+                table.insert(state, idx);
+                idx
+            }
+            None => NodeIdx(self.nodes.push(Node::new_unexpanded(state, None))),
+        }
+    }
+
+    // The node for this successor of parent: the existing node for its state if there is one in
+    // the transposition table, otherwise a new unexpanded node.
+    fn add_successor(
+        &self,
+        table: &mut Option<std::sync::MutexGuard<'_, HashMap<State, NodeIdx>>>,
+        state: State,
+        parent: NodeIdx,
+    ) -> NodeIdx {
+        match table {
+            Some(table) => {
+                if let Some(&idx) = table.get(&state) {
+                    self.transposition_hits.fetch_add(1, Ordering::Relaxed);
+                    return idx;
+                }
+                let idx = NodeIdx(
+                    self.nodes
+                        .push(Node::new_unexpanded(state.clone(), Some(parent))),
+                );
+                table.insert(state, idx);
+                idx
+            }
+            None => NodeIdx(self.nodes.push(Node::new_unexpanded(state, Some(parent)))),
+        }
+    }
+}
+
+impl<State> std::default::Default for Tree<State> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State> std::ops::Index<NodeIdx> for Tree<State> {
+    type Output = Node<State>;
+    fn index(&self, idx: NodeIdx) -> &Node<State> {
+        self.nodes.get(idx.0)
+    }
+}
+
+impl<State> std::ops::IndexMut<NodeIdx> for Tree<State> {
+    fn index_mut(&mut self, idx: NodeIdx) -> &mut Node<State> {
+        self.nodes.get_mut(idx.0)
+    }
+}
+
+impl<State> std::fmt::Debug for Tree<State> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Tree")
+            .field("node_count", &self.node_count())
+            .field("memory_bytes", &self.memory_bytes())
+            .field("transposition_hits", &self.transposition_hits())
+            .finish()
+    }
+}
+
+// The exclusive right to expand one node, see Tree::begin_expansion
+pub struct Expanding<'a, State> {
+    tree: &'a Tree<State>,
+    idx: NodeIdx,
+    finished: bool,
+}
+
+impl<'a, State> Expanding<'a, State> {
+    pub fn node(&self) -> &'a Node<State> {
+        &self.tree[self.idx]
+    }
+
+    // Record the expert's q for the node, add an edge to every successor, and publish the node as expanded
+    pub fn finish<I>(mut self, value: f32, successors: I)
+    where
+        I: IntoIterator<Item = Successor<State>>,
+        State: Hash + Eq + Clone,
+    {
+        let tree = self.tree;
+        let idx = self.idx;
+        // Held across the whole expansion, so that two threads can't add the same state twice
+        let mut table = tree
+            .transpositions
+            .as_ref()
+            .map(|table| table.lock().unwrap());
+        let edges: Vec<Edge> = successors
+            .into_iter()
+            .map(|s| {
+                let child = tree.add_successor(&mut table, s.state, idx);
+                Edge::new(s.action, s.prior, s.raw_prior, child)
+            })
+            .collect();
+        drop(table);
+        tree.edge_count.fetch_add(edges.len(), Ordering::Relaxed);
+        let node = &tree[idx];
+        // Ok, because this thread holds the node's EXPANDING claim
+        unsafe {
+            *node.edges.get() = edges.into_boxed_slice();
+            *node.value.get() = value;
+        }
+        tree.end_expansion(idx, EXPANDED);
+        self.finished = true;
+    }
+}
+
+impl<'a, State> Drop for Expanding<'a, State> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.tree.end_expansion(self.idx, UNEXPANDED);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TreeSnapshot<State> {
+    nodes: Vec<NodeSnapshot<State>>, // Indexed by NodeIdx
+    uses_transpositions: bool,
+    transposition_hits: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NodeSnapshot<State> {
+    state: State,
+    parent: Option<usize>,
+    visits: usize,
+    expanded: Option<(f32, Vec<EdgeSnapshot>)>, // The expert's q and the edges, once the node is expanded
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EdgeSnapshot {
+    action: usize,
+    prior: f32,
+    raw_prior: f32,
+    child: usize,
+    visit_count: usize,
+    total_value: f64,
+    wins: usize,
+    losses: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn successors(states: &[u32]) -> Vec<Successor<u32>> {
+        states
+            .iter()
+            .enumerate()
+            .map(|(action, &state)| Successor {
+                action,
+                prior: 0.5,
+                raw_prior: 0.5,
+                state,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_one_thread_expands_a_node() {
+        let tree = Tree::new();
+        let root = tree.add_root(0u32);
+        let winners: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|thread| {
+                    let tree = &tree;
+                    scope.spawn(move || match tree.begin_expansion(root) {
+                        Some(expanding) => {
+                            expanding.finish(0.25, successors(&[10 + thread, 20 + thread]));
+                            1
+                        }
+                        None => 0,
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(winners, 1);
+        assert_eq!(tree[root].expansion(), Expansion::Expanded);
+        assert_eq!(tree[root].value(), Some(0.25));
+        assert_eq!(tree[root].edges().len(), 2);
+        assert_eq!(tree.node_count(), 3);
+        for edge in tree[root].edges() {
+            assert_eq!(tree[edge.child()].parent(), Some(root));
+        }
+        assert!(tree.begin_expansion(root).is_none());
+    }
+
+    #[test]
+    fn unfinished_expansions_are_reverted() {
+        let tree = Tree::new();
+        let root = tree.add_root(0u32);
+        {
+            let expanding = tree.begin_expansion(root).unwrap();
+            assert_eq!(expanding.node().expansion(), Expansion::Expanding);
+            assert!(tree.begin_expansion(root).is_none());
+        }
+        assert_eq!(tree[root].expansion(), Expansion::Unexpanded);
+        assert!(tree[root].edges().is_empty());
+        assert_eq!(tree[root].value(), None);
+        tree.begin_expansion(root)
+            .unwrap()
+            .finish(0.0, successors(&[1]));
+        assert_eq!(tree[root].edges().len(), 1);
+    }
+
+    #[test]
+    fn retain_subtree_renumbers_the_kept_nodes() {
+        // 0 -> {1, 2}, 2 -> {3, 4}, 4 -> {5}
+        let mut tree = Tree::new();
+        let root = tree.add_root(0u32);
+        tree.begin_expansion(root)
+            .unwrap()
+            .finish(0.0, successors(&[1, 2]));
+        let kept = tree[root].edges()[1].child();
+        tree.begin_expansion(kept)
+            .unwrap()
+            .finish(0.5, successors(&[3, 4]));
+        let grandchild = tree[kept].edges()[1].child();
+        tree.begin_expansion(grandchild)
+            .unwrap()
+            .finish(0.0, successors(&[5]));
+        tree[kept].edges()[0].add_virtual_loss();
+        tree[kept].edges()[0].backup(1.0, 1, 0);
+
+        let root = tree.retain_subtree(kept);
+        assert_eq!(root, NodeIdx(0));
+        assert_eq!(tree.node_count(), 4);
+        assert_eq!(*tree[root].state(), 2);
+        assert_eq!(tree[root].parent(), None);
+        assert_eq!(tree[root].value(), Some(0.5));
+        let states: Vec<u32> = tree[root]
+            .edges()
+            .iter()
+            .map(|edge| *tree[edge.child()].state())
+            .collect();
+        assert_eq!(states, vec![3, 4]);
+        for edge in tree[root].edges() {
+            assert_eq!(tree[edge.child()].parent(), Some(root));
+        }
+        assert_eq!(tree[root].edges()[0].visit_count(), 1);
+        let four = tree[root].edges()[1].child();
+        let five = tree[four].edges()[0].child();
+        assert_eq!(*tree[five].state(), 5);
+        assert_eq!(tree[five].parent(), Some(four));
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let tree = Tree::with_transpositions();
+        let root = tree.add_root(0u32);
+        tree.begin_expansion(root)
+            .unwrap()
+            .finish(0.75, successors(&[1, 2]));
+        let child = tree[root].edges()[0].child();
+        // 1 -> 2 is a transposition of 0 -> 2
+        tree.begin_expansion(child)
+            .unwrap()
+            .finish(-0.5, successors(&[2]));
+        tree[root].add_visit();
+        tree[root].edges()[0].add_virtual_loss();
+        tree[root].edges()[0].backup(-1.0, 0, 1);
+
+        let restored = Tree::from_snapshot(tree.snapshot()).unwrap();
+        assert_eq!(restored.node_count(), tree.node_count());
+        assert!(restored.uses_transpositions());
+        assert_eq!(restored.transposition_hits(), 1);
+        for i in 0..tree.node_count() {
+            let (a, b) = (&tree[NodeIdx(i)], &restored[NodeIdx(i)]);
+            assert_eq!(a.state(), b.state());
+            assert_eq!(a.parent(), b.parent());
+            assert_eq!(a.visits(), b.visits());
+            assert_eq!(a.value(), b.value());
+            assert_eq!(a.edges().len(), b.edges().len());
+            for (a, b) in a.edges().iter().zip(b.edges()) {
+                assert_eq!(a.action(), b.action());
+                assert_eq!(a.child(), b.child());
+                assert_eq!(a.prior(), b.prior());
+                assert_eq!(a.visit_count(), b.visit_count());
+                assert_eq!(a.total_value(), b.total_value());
+                assert_eq!(a.losses(), b.losses());
+            }
+        }
+        // The transposition table comes back too
+        restored
+            .begin_expansion(NodeIdx(2))
+            .unwrap()
+            .finish(0.0, successors(&[1]));
+        assert_eq!(restored.node_count(), tree.node_count());
+        assert_eq!(restored.transposition_hits(), 2);
+    }
+
+    #[test]
+    fn snapshots_with_dangling_children_are_rejected() {
+        let tree = Tree::new();
+        let root = tree.add_root(0u32);
+        tree.begin_expansion(root)
+            .unwrap()
+            .finish(0.0, successors(&[1, 2]));
+        let mut snapshot = tree.snapshot();
+        if let Some((_, edges)) = snapshot.nodes[0].expanded.as_mut() {
+            edges[1].child = 3;
+        }
+        assert!(Tree::from_snapshot(snapshot).is_err());
+    }
+}
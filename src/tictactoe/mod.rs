@@ -2,4 +2,4 @@ mod expert;
 pub mod gen;
 mod state;
 
-pub use self::state::State;
+pub use self::state::{State, TicTacToe};
@@ -2,6 +2,7 @@
 
 use crate::{game, game::GameStatus};
 use std::fmt;
+#[cfg(feature = "tensorflow")]
 use tensorflow as tf;
 
 #[derive(Clone, Debug, PartialEq, Copy)]
@@ -16,6 +17,7 @@ struct ParseError {
 
 #[derive(Debug)]
 pub enum RootErrorCause {
+    #[cfg(feature = "tensorflow")]
     Tf(tf::Status),
     Io(::std::io::Error),
 }
@@ -26,6 +28,7 @@ pub struct TicTacToeError {
     pub root_error: RootErrorCause,
 }
 
+#[cfg(feature = "tensorflow")]
 impl From<tf::Status> for TicTacToeError {
     fn from(e: tf::Status) -> TicTacToeError {
         TicTacToeError {
@@ -48,7 +51,7 @@ pub struct TicTacToe {
     all_actions: Vec<usize>,
 }
 impl TicTacToe {
-    fn new() -> Self {
+    pub fn new() -> Self {
         TicTacToe {
             all_actions: vec![0, 1, 2, 3, 4, 5, 6, 7, 8],
         }
@@ -65,11 +68,9 @@ impl game::Game for TicTacToe {
     // All the action indexes that are legal for a given State
     // nonlegal actions will be forced to 0 probability by the search engine
     fn legal_actions(&self, state: &Self::State) -> Vec<bool> {
-        let mut x = Vec::with_capacity(9);
-        for i in 0..9 {
-            x[i] = !state.board[0][i] && !state.board[1][i];
-        }
-        x
+        (0..9)
+            .map(|i| !state.board[0][i] && !state.board[1][i])
+            .collect()
     }
     // The given a state and an action on that state, the successor state
     fn successor(&self, state: &Self::State, action: usize) -> Self::State {
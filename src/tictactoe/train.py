@@ -169,10 +169,11 @@ def write_summaries(write_training_summaries, summaries, global_step_val):
 
 
 def write_snapshot(sess, saver, global_step_val, snapshot_id=None):
+    version = "{}-{}".format(
+        datetime.datetime.now().replace(microsecond=0).isoformat(), global_step_val
+    )
     if snapshot_id is None:
-        snapshot_id = "{}-{}".format(
-            datetime.datetime.now().replace(microsecond=0).isoformat(), global_step_val
-        )
+        snapshot_id = version
     snapshot_dir = model_dir + "/" + snapshot_id
     saver_dir = snapshot_dir + "/checkpoints/"
     saver_prefix = saver_dir + "model"
@@ -182,10 +183,10 @@ def write_snapshot(sess, saver, global_step_val, snapshot_id=None):
         None
 
     saver.save(sess, saver_prefix, global_step_val)
-    save_savedmodel(sess, snapshot_dir)
+    save_savedmodel(sess, snapshot_dir, version)
 
 
-def save_savedmodel(sess, snapshot_dir):
+def save_savedmodel(sess, snapshot_dir, version):
     # SavedModels are "hermetic" complete representations of the training model, meant for consumption
     # across binary boundaries. They have the unfortunate side effect of not being easy to continue training with.
     # In essence, they're frozen snapshots of the model at a moment in time. We periodically save snapshots
@@ -208,6 +209,9 @@ def save_savedmodel(sess, snapshot_dir):
         inputs={"example": example},
         outputs={"softmax": softmax, "logits": logits},
     )
+    # Game generators reload the champion when its version changes, and record it in their examples
+    with open(snapshot_dir + "/version", "w") as version_file:
+        version_file.write(version)
     fcntl.flock(lock, fcntl.LOCK_UN)
 
     # builder = tf.saved_model.builder.SavedModelBuilder(savedmodel_dir)
@@ -0,0 +1,204 @@
+//! Runs OnnxInferenceEngine on tiny models that are written out byte by byte, so that the test doesn't
+//! need Python or a checked-in binary. Each model casts its [n, 1] uint8 input to float and multiplies
+//! it by a weight matrix, so the expected priors are easy to work out by hand.
+#![cfg(feature = "onnx")]
+use seraphim::error::InferenceError;
+use seraphim::inference::{signature::ModelOutputs, Inference, OnnxInferenceEngine};
+use std::path::{Path, PathBuf};
+
+// Protobuf wire format, just enough of it for an ONNX ModelProto
+fn varint(mut value: u64, bytes: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn message(field: u64, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    varint(field << 3 | 2, &mut bytes);
+    varint(value.len() as u64, &mut bytes);
+    bytes.extend_from_slice(value);
+    bytes
+}
+
+fn int(field: u64, value: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    varint(field << 3, &mut bytes);
+    varint(value, &mut bytes);
+    bytes
+}
+
+const FLOAT: u64 = 1;
+const UINT8: u64 = 2;
+
+// ValueInfoProto { name = 1; TypeProto type = 2 { Tensor tensor_type = 1 { elem_type = 1; shape = 2 } } }.
+// A dimension of None is the symbolic batch size.
+fn value_info(name: &str, elem_type: u64, dims: &[Option<u64>]) -> Vec<u8> {
+    let dims: Vec<u8> = dims
+        .iter()
+        .flat_map(|dim| match dim {
+            Some(dim) => message(1, &int(1, *dim)),
+            None => message(1, &message(2, b"N")),
+        })
+        .collect();
+    let tensor_type = [int(1, elem_type), message(2, &dims)].concat();
+    [
+        message(1, name.as_bytes()),
+        message(2, &message(1, &tensor_type)),
+    ]
+    .concat()
+}
+
+// NodeProto { input = 1; output = 2; name = 3; op_type = 4; attribute = 5 }
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut node: Vec<u8> = inputs
+        .iter()
+        .flat_map(|input| message(1, input.as_bytes()))
+        .collect();
+    node.extend(message(2, output.as_bytes()));
+    node.extend(message(3, output.as_bytes()));
+    node.extend(message(4, op_type.as_bytes()));
+    for attribute in attributes {
+        node.extend(message(5, attribute));
+    }
+    node
+}
+
+// TensorProto { dims = 1; data_type = 2; name = 8; raw_data = 9 }
+fn weights(name: &str, dims: &[u64], values: &[f32]) -> Vec<u8> {
+    let mut tensor: Vec<u8> = dims.iter().flat_map(|dim| int(1, *dim)).collect();
+    tensor.extend(int(2, FLOAT));
+    tensor.extend(message(8, name.as_bytes()));
+    let raw: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    tensor.extend(message(9, &raw));
+    tensor
+}
+
+// A model with two actions. Packed, its one output is x * [0.25, 0.7, 0.3], i.e. [q, ps...]. Separate,
+// its policy output is x * [0.7, 0.3] and its value output is x * -0.5.
+fn model(separate: bool) -> Vec<u8> {
+    // AttributeProto { name = 1; i = 3; type = 20 (INT) }
+    let cast_to_float = [message(1, b"to"), int(3, FLOAT), int(20, 2)].concat();
+    let mut graph = message(1, &node("Cast", &["x"], "xf", &[cast_to_float]));
+    if separate {
+        graph.extend(message(1, &node("MatMul", &["xf", "wp"], "policy", &[])));
+        graph.extend(message(1, &node("MatMul", &["xf", "wv"], "value", &[])));
+        graph.extend(message(5, &weights("wp", &[1, 2], &[0.7, 0.3])));
+        graph.extend(message(5, &weights("wv", &[1, 1], &[-0.5])));
+    } else {
+        graph.extend(message(1, &node("MatMul", &["xf", "w"], "packed", &[])));
+        graph.extend(message(5, &weights("w", &[1, 3], &[0.25, 0.7, 0.3])));
+    }
+    graph.extend(message(2, b"graph"));
+    graph.extend(message(11, &value_info("x", UINT8, &[None, Some(1)])));
+    if separate {
+        graph.extend(message(12, &value_info("policy", FLOAT, &[None, Some(2)])));
+        graph.extend(message(12, &value_info("value", FLOAT, &[None, Some(1)])));
+    } else {
+        graph.extend(message(12, &value_info("packed", FLOAT, &[None, Some(3)])));
+    }
+    // ModelProto { ir_version = 1; opset_import = 8 { domain = 1; version = 2 }; graph = 7 }
+    [
+        int(1, 7),
+        message(8, &[message(1, b""), int(2, 13)].concat()),
+        message(7, &graph),
+    ]
+    .concat()
+}
+
+fn write_model(name: &str, separate: bool) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("seraphim-onnx-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, model(separate)).unwrap();
+    path
+}
+
+fn separate() -> ModelOutputs {
+    ModelOutputs::Separate {
+        policy: "policy".to_string(),
+        value: "value".to_string(),
+    }
+}
+
+fn assert_wrong_output_length(engine: &mut OnnxInferenceEngine, path: &Path, output: &str) {
+    let err = engine.infer_batch(&[&[1], &[2]]).unwrap_err();
+    match err.downcast::<InferenceError>() {
+        Ok(InferenceError::WrongOutputLength { op, dir, .. }) => {
+            assert_eq!(op, output);
+            assert_eq!(dir, path);
+        }
+        other => panic!("Expected WrongOutputLength, got {:?}", other),
+    }
+}
+
+#[test]
+fn packed_outputs() {
+    let path = write_model("packed.onnx", false);
+    let packed = ModelOutputs::Packed("packed".to_string());
+    let mut engine = OnnxInferenceEngine::from_path(&path, &packed, 2).unwrap();
+
+    let priors = engine.infer(&[2]).unwrap();
+    assert_eq!(priors.q, 0.5);
+    assert_eq!(priors.ps, vec![1.4, 0.6]);
+    let priors = engine.infer_batch(&[&[1], &[4], &[0]]).unwrap();
+    let qs: Vec<f32> = priors.iter().map(|priors| priors.q).collect();
+    assert_eq!(qs, vec![0.25, 1.0, 0.0]);
+    assert_eq!(priors[2].ps, vec![0.0, 0.0]);
+    assert!(engine.infer_batch(&[]).unwrap().is_empty());
+
+    // Clones share the model
+    let mut clone = engine.try_clone().unwrap();
+    assert_eq!(clone.infer(&[1]).unwrap().ps, vec![0.7, 0.3]);
+
+    // Inputs must have the model's feature length
+    match engine
+        .infer(&[1, 2])
+        .unwrap_err()
+        .downcast::<InferenceError>()
+    {
+        Ok(InferenceError::RaggedBatch { expected, actual }) => {
+            assert_eq!((expected, actual), (1, 2))
+        }
+        other => panic!("Expected RaggedBatch, got {:?}", other),
+    }
+
+    // Rows of 3 floats can't hold the priors of 5 actions
+    let mut wrong = OnnxInferenceEngine::from_path(&path, &packed, 5).unwrap();
+    assert_wrong_output_length(&mut wrong, &path, "packed");
+}
+
+#[test]
+fn separate_outputs() {
+    let path = write_model("separate.onnx", true);
+    let mut engine = OnnxInferenceEngine::from_path(&path, &separate(), 2).unwrap();
+
+    let priors = engine.infer_batch(&[&[2], &[1]]).unwrap();
+    assert_eq!(priors[0].q, -1.0);
+    assert_eq!(priors[0].ps, vec![1.4, 0.6]);
+    assert_eq!(priors[1].q, -0.5);
+    assert_eq!(priors[1].ps, vec![0.7, 0.3]);
+
+    // The policy output has 2 floats per state, not 3
+    let mut wrong = OnnxInferenceEngine::from_path(&path, &separate(), 3).unwrap();
+    assert_wrong_output_length(&mut wrong, &path, "policy");
+}
+
+#[test]
+fn unknown_outputs_fail_to_load() {
+    let path = write_model("unknown.onnx", false);
+    let missing = ModelOutputs::Packed("softmax".to_string());
+    assert!(OnnxInferenceEngine::from_path(&path, &missing, 2).is_err());
+    assert!(
+        OnnxInferenceEngine::from_path(path.with_extension("missing"), &separate(), 2).is_err()
+    );
+}